@@ -1,173 +1,204 @@
 use anyhow::{Context, Result};
-use askama::Template;
+use camino::Utf8Path;
 use std::fs::{self, create_dir_all, File};
 use std::path::{Path, PathBuf};
 
 use crate::android::android_target::AndroidTarget;
+use crate::android::elf::runtime_dependencies;
+use crate::android::jar::{compile_kotlin_with_output, write_empty_jar, zip_dir};
+use crate::android::manifest::ManifestSettings;
+use crate::android::ndk::Ndk;
 use crate::common::models::{Config, LibType, Mode};
 use crate::common::path::recreate_dir;
-use crate::console::step::run_step;
-use crate::common::templating::{AndroidManifest, GradleProperties};
+use crate::console::{messages::{info, warning}, step::run_step};
 
+/// Contents of `META-INF/com/android/build/gradle/aar-metadata.properties` checked by the Android Gradle Plugin
+const AAR_METADATA: &str = "aarFormatVersion=1.0\naarMetadataVersion=1.0\nminCompileSdk=1\n";
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_aar_with_output(
     targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
     lib_name: &str,
     package_name: &str,
     aar_name: &str,
+    classpath: &[PathBuf],
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
     mode: Mode,
     lib_type: LibType,
     config: &Config,
 ) -> Result<()> {
-    run_step(config, "Creating Android AAR package...", || {
-        let output_dir = PathBuf::from(aar_name);
+    let sources_dir = config.generated_dir().join("kotlin").into_std_path_buf();
+    let classes_jar = target_dir
+        .join("rusify")
+        .join("android")
+        .join(format!("{lib_name}-classes.jar"))
+        .into_std_path_buf();
+    let compiled = compile_kotlin_with_output(&sources_dir, &classes_jar, classpath, config)?;
+
+    let unresolved = run_step(config, "Creating Android AAR package...", || {
+        let output_dir = config.output_path(aar_name).into_std_path_buf();
         recreate_dir(&output_dir)?;
-        
-        create_aar(
+
+        let unresolved = create_aar(
             targets,
+            ndk,
+            target_dir,
             lib_name,
             package_name,
+            compiled.then_some(classes_jar.as_path()),
+            manifest,
+            proguard_rules,
             &output_dir,
             mode,
             lib_type,
-        )
+        )?;
+
+        // Sources are shipped separately so IDEs can show them and consumers without kotlinc can compile them
+        let sources_jar = config.output_path(&format!("{aar_name}-sources.jar"));
+        zip_dir(&sources_dir, sources_jar.as_std_path(), true)?;
+
+        Ok(unresolved)
     })
     .map_err(|e| {
         anyhow::anyhow!(
             "Failed to create AAR package due to the following error: \n {}",
             e
         )
-    })
+    })?;
+
+    warn_unresolved_dependencies(config, unresolved);
+
+    info!(config, "Created AAR package at: {}.aar", config.output_path(aar_name));
+
+    Ok(())
 }
 
+/// Creates the AAR and returns the runtime dependencies that could not be bundled
+///
+/// The staging directory `output_dir` is laid out like the AAR: `AndroidManifest.xml`, `classes.jar`,
+/// `jni/<abi>/*.so`, `R.txt` and `proguard.txt`. Without `classes_jar`, an empty `classes.jar` is added.
+#[allow(clippy::too_many_arguments)]
 fn create_aar(
     targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
     lib_name: &str,
     package_name: &str,
+    classes_jar: Option<&Path>,
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
     output_dir: &Path,
     mode: Mode,
     lib_type: LibType,
-) -> Result<()> {
-    // Create the necessary directory structure for the AAR
+) -> Result<Vec<String>> {
     let jni_dir = output_dir.join("jni");
     create_dir_all(&jni_dir)?;
-    
-    // Create manifests directory
-    let manifests_dir = output_dir.join("manifests");
-    create_dir_all(&manifests_dir)?;
-    
+
     // Generate AndroidManifest.xml
-    let android_manifest = AndroidManifest { package_name };
-    let manifest_content = android_manifest.render()
-        .context("Failed to render AndroidManifest.xml template")?;
-    
-    // Write AndroidManifest.xml
-    let manifest_path = manifests_dir.join("AndroidManifest.xml");
-    fs::write(&manifest_path, manifest_content)
+    let manifest_content = manifest.render(Some(package_name))?;
+
+    fs::write(output_dir.join("AndroidManifest.xml"), manifest_content)
         .context("Failed to write AndroidManifest.xml")?;
-    
-    // Copy native libraries
-    for target in targets {
-        let arch_dir = jni_dir.join(target.ndk_arch);
-        create_dir_all(&arch_dir)?;
-        
-        let lib_path = target.library_path(lib_name, mode, lib_type);
-        let dest_path = arch_dir.join(format!("lib{}.so", lib_name));
-        
-        fs::copy(lib_path, dest_path)
-            .context(format!("Failed to copy library for {}", target.display_name()))?;
-    }
-    
-    // Copy Kotlin sources
-    let kotlin_dir = output_dir.join("kotlin");
-    create_dir_all(&kotlin_dir)?;
-    
-    // Create package directory structure
-    let package_path = package_name.replace(".", "/");
-    let package_dir = kotlin_dir.join(&package_path);
-    create_dir_all(&package_dir)?;
-    
-    // Copy Kotlin files from generated directory
-    let generated_dir = Path::new("./generated/kotlin").join(&package_path);
-    if generated_dir.exists() {
-        for entry in fs::read_dir(generated_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "kt") {
-                let dest_path = package_dir.join(path.file_name().unwrap());
-                fs::copy(&path, dest_path)?;
-            }
+
+    let classes_path = output_dir.join("classes.jar");
+    match classes_jar {
+        Some(classes_jar) => {
+            fs::copy(classes_jar, &classes_path).context("Failed to copy classes.jar")?;
         }
+        // The Android Gradle Plugin expects a classes.jar even if there are no classes
+        None => write_empty_jar(&classes_path)?,
     }
-    
-    // Create gradle.properties
-    let gradle_props = GradleProperties { lib_name };
-    let gradle_content = gradle_props.render()
-        .context("Failed to render gradle.properties template")?;
-    
-    fs::write(output_dir.join("gradle.properties"), gradle_content)
-        .context("Failed to write gradle.properties")?;
-    
+
+    let unresolved = copy_native_libraries(targets, ndk, target_dir, lib_name, mode, lib_type, &jni_dir)?;
+
     // Create a basic R.txt file (required for AAR)
     File::create(output_dir.join("R.txt"))?;
-    
-    // Create a simple proguard.txt
-    fs::write(
-        output_dir.join("proguard.txt"),
-        "-keep class ".to_string() + package_name + ".** { *; }\n",
-    )?;
-    
-    // Create META-INF directory
-    let meta_inf_dir = output_dir.join("META-INF");
-    create_dir_all(&meta_inf_dir)?;
-    
+
+    // Consumer rules applied by R8 when the app is minified
+    fs::write(output_dir.join("proguard.txt"), proguard_rules)
+        .context("Failed to write proguard.txt")?;
+
+    let metadata_dir = output_dir
+        .join("META-INF")
+        .join("com")
+        .join("android")
+        .join("build")
+        .join("gradle");
+    create_dir_all(&metadata_dir)?;
+    fs::write(metadata_dir.join("aar-metadata.properties"), AAR_METADATA)?;
+
     // Generate zip file (AAR is a zip file with a specific structure)
     let aar_path = PathBuf::from(format!("{}.aar", output_dir.to_str().unwrap()));
-    
-    // Create a zip file in Rust without external dependencies
-    let aar_file = std::fs::File::create(&aar_path)
-        .context("Failed to create AAR file")?;
-    
-    let mut zip = zip::ZipWriter::new(aar_file);
-    let options = zip::write::FileOptions::default()
-        .compression_method(zip::CompressionMethod::Deflated)
-        .unix_permissions(0o755);
-    
-    // Helper function to recursively add files to the zip
-    fn add_dir_to_zip<T: std::io::Write + std::io::Seek>(
-        zip: &mut zip::ZipWriter<T>,
-        options: &zip::write::FileOptions,
-        dir: &Path,
-        root: &Path,
-    ) -> Result<()> {
-        for entry in std::fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            let relative_path = path.strip_prefix(root)?;
-            
-            if path.is_dir() {
-                zip.add_directory(
-                    relative_path.to_string_lossy().as_ref(),
-                    *options,
-                )?;
-                add_dir_to_zip(zip, options, &path, root)?;
-            } else {
-                zip.start_file(relative_path.to_string_lossy().as_ref(), *options)?;
-                let mut file = std::fs::File::open(&path)?;
-                std::io::copy(&mut file, zip)?;
+    zip_dir(output_dir, &aar_path, false).context("Failed to create AAR archive")?;
+
+    Ok(unresolved)
+}
+
+/// Copies the native libraries of all targets into `<jni_dir>/<abi>` and returns the runtime
+/// dependencies that could not be bundled
+///
+/// Dynamic libraries are copied together with the shared libraries of the NDK they depend on.
+pub(crate) fn copy_native_libraries(
+    targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    mode: Mode,
+    lib_type: LibType,
+    jni_dir: &Path,
+) -> Result<Vec<String>> {
+    let mut unresolved = Vec::new();
+    for target in targets {
+        let arch_dir = jni_dir.join(target.ndk_arch);
+        create_dir_all(&arch_dir)?;
+
+        let lib_path = target.library_path(target_dir, lib_name, mode, lib_type);
+        let dest_path = arch_dir.join(format!("lib{}.so", lib_name));
+
+        // Dynamic libraries are packaged after post-processing, e.g. with their debug info stripped
+        let packaged_path = match lib_type {
+            LibType::Dynamic => target.packaged_library_path(target_dir, lib_name, mode).into_string(),
+            LibType::Static => lib_path.clone(),
+        };
+        fs::copy(&packaged_path, dest_path)
+            .context(format!("Failed to copy library for {}", target.display_name()))?;
+
+        // Bundle shared libraries of the NDK the library links against, e.g. libc++_shared.so
+        if lib_type == LibType::Dynamic {
+            for arch in target.architectures() {
+                let dependencies =
+                    runtime_dependencies(ndk, arch, target.api_level, Path::new(&lib_path))?;
+                for dependency in &dependencies.resolved {
+                    let file_name = dependency
+                        .file_name()
+                        .context("Runtime dependency has no file name")?;
+                    fs::copy(dependency, arch_dir.join(file_name)).context(format!(
+                        "Failed to copy runtime dependency {}",
+                        dependency.display()
+                    ))?;
+                }
+                unresolved.extend(
+                    dependencies
+                        .unresolved
+                        .into_iter()
+                        .map(|name| format!("{name} ({})", target.display_name())),
+                );
             }
         }
-        Ok(())
     }
-    
-    // Add all files and directories to the zip
-    add_dir_to_zip(&mut zip, &options, output_dir, output_dir)
-        .context("Failed to add files to AAR archive")?;
-    
-    // Finalize the zip file
-    zip.finish()
-        .context("Failed to finalize AAR archive")?;
-    
-    println!("Created AAR package at: {}", aar_path.display());
-    
-    Ok(())
-}
\ No newline at end of file
+
+    Ok(unresolved)
+}
+
+/// Warns about runtime dependencies that have to be provided by the app
+pub(crate) fn warn_unresolved_dependencies(config: &Config, unresolved: Vec<String>) {
+    for library in unresolved {
+        warning!(
+            config,
+            "Could not find the runtime dependency {library} in the NDK. It has to be provided by the app, otherwise loading the library will fail."
+        );
+    }
+}
@@ -1,6 +1,9 @@
-use crate::common::metadata::{metadata, MetadataExt};
+use crate::android::ndk::{ar_path, clang_path, clang_target};
 use crate::common::models::{FeatureOptions, LibType, Mode};
+use camino::{Utf8Path, Utf8PathBuf};
 use execute::command;
+use std::ffi::OsString;
+use std::path::Path;
 use std::process::Command;
 
 #[derive(Debug, Clone)]
@@ -11,6 +14,17 @@ pub struct AndroidTarget {
     pub api_level: u32,
 }
 
+/// The kind of Android package that is created
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AndroidOutput {
+    /// Android library archive with the compiled Kotlin bindings and native libraries
+    Aar,
+    /// Gradle library module with the Kotlin bindings as sources, to be included in a Gradle build
+    GradleModule,
+    /// Kotlin Multiplatform module sharing the Kotlin bindings between Android and the JVM desktop
+    KmpModule,
+}
+
 const ARCH_COUNT: usize = 4;
 #[derive(clap::ValueEnum, Clone, Copy, Debug)]
 pub enum AndroidArch {
@@ -71,11 +85,19 @@ impl AndroidArch {
 }
 
 impl AndroidTarget {
-    pub(crate) fn cargo_build_commands(&self, mode: Mode, features: &FeatureOptions) -> Vec<Command> {
+    pub(crate) fn cargo_build_commands(
+        &self,
+        manifest_path: &Utf8Path,
+        ndk_home: &Path,
+        target_dir: &Utf8Path,
+        mode: Mode,
+        features: &FeatureOptions,
+    ) -> Vec<Command> {
         self.architectures
             .iter()
             .map(|arch| {
                 let mut cmd = command("cargo build");
+                cmd.arg("--manifest-path").arg(manifest_path);
                 cmd.arg("--target").arg(arch);
 
                 match mode {
@@ -94,260 +116,78 @@ impl AndroidTarget {
                 if features.no_default_features {
                     cmd.arg("--no-default-features");
                 }
-                
-                // Pass special environment variables for the ring crate
-                cmd.env("CARGO_TERM_COLOR", "always");
-                
-                // Configure environment variables from current process
-                if let Ok(path) = std::env::var("PATH") {
-                    cmd.env("PATH", path);
-                }
-                
-                // Compiler variables
-                if let Ok(cc) = std::env::var("CC") {
-                    cmd.env("CC", cc);
-                }
-                let cc_var = format!("CC_{}", arch.replace("-", "_"));
-                if let Ok(cc_arch) = std::env::var(&cc_var) {
-                    cmd.env(cc_var, cc_arch);
-                }
-                if let Ok(target_cc) = std::env::var("TARGET_CC") {
-                    cmd.env("TARGET_CC", target_cc);
-                }
-                
-                // Archiver variables
-                if let Ok(ar) = std::env::var("AR") {
-                    cmd.env("AR", ar);
-                }
-                let ar_var = format!("AR_{}", arch.replace("-", "_"));
-                if let Ok(ar_arch) = std::env::var(&ar_var) {
-                    cmd.env(ar_var, ar_arch);
-                }
-                if let Ok(target_ar) = std::env::var("TARGET_AR") {
-                    cmd.env("TARGET_AR", target_ar);
-                }
-                
-                // Pass CFLAGS variables
-                let cflags_var = format!("CFLAGS_{}", arch.replace("-", "_"));
-                if let Ok(cflags) = std::env::var(&cflags_var) {
-                    cmd.env(cflags_var, cflags);
-                }
-                if let Ok(target_cflags) = std::env::var("TARGET_CFLAGS") {
-                    cmd.env("TARGET_CFLAGS", target_cflags);
-                }
+
+                cmd.envs(self.toolchain_environment(ndk_home, target_dir, arch));
 
                 cmd
             })
             .collect()
     }
 
-    fn setup_commands(&self) -> Vec<Command> {
-        // Don't use commands for setup - we'll handle this directly
-        Vec::new()
-    }
-    
-    pub fn setup_environment(&self) -> Result<(), anyhow::Error> {
-        // Check if ANDROID_NDK_HOME is set
-        let ndk_home = match std::env::var("ANDROID_NDK_HOME") {
-            Ok(path) => path,
-            Err(_) => {
-                return Err(anyhow::anyhow!(
-                    "Error: ANDROID_NDK_HOME environment variable is not set. Please install Android NDK and set ANDROID_NDK_HOME."
-                ));
-            }
-        };
-        
-        // Handle platform-specific path separators and directories
-        let host_os = if cfg!(target_os = "macos") {
-            "darwin"
-        } else if cfg!(target_os = "windows") {
-            "windows"
-        } else {
-            "linux"
-        };
-        
-        // Handle platform-specific path construction
-        let path_sep = if cfg!(target_os = "windows") { "\\" } else { "/" };
-        
-        // Create toolchain path in platform-specific way
-        let toolchain_path = format!(
-            "{}{}toolchains{}llvm{}prebuilt{}{}-x86_64", 
-            ndk_home, path_sep, path_sep, path_sep, path_sep, host_os
-        );
-        
-        // Create patched paths for tools based on platform
-        let arch = self.architectures[0];
-        let api = self.api_level;
-        
-        // Handle Windows-specific exe extension
-        let exe_ext = if cfg!(target_os = "windows") { ".exe" } else { "" };
-        
-        // Create .cargo directory if it doesn't exist
-        std::fs::create_dir_all(".cargo")?;
-        
-        // Create paths for tools
-        let ar_path = format!("{}{}bin{}llvm-ar{}", toolchain_path, path_sep, path_sep, exe_ext);
-        let clang_path = format!("{}{}bin{}{}{}-clang{}", 
-            toolchain_path, path_sep, path_sep, arch, api, exe_ext);
-        
-        // On Windows, use forward slashes in config file even though the OS uses backslashes
-        let ar_config_path = ar_path.replace("\\", "/");
-        let clang_config_path = clang_path.replace("\\", "/");
-        
-        // Create .cargo/config.toml with absolute paths
-        let config_content = format!(
-            "[target.{}]\n\
-            ar = \"{}\"\n\
-            linker = \"{}\"\n",
-            arch, ar_config_path, clang_config_path
-        );
-        
-        std::fs::write(".cargo/config.toml", config_content)?;
-        
-        // Verify that the tools exist
-        if !std::path::Path::new(&ar_path).exists() {
-            return Err(anyhow::anyhow!(
-                "Android NDK tool not found: {}. Please check your ANDROID_NDK_HOME setting.", 
-                ar_path
-            ));
-        }
-        
-        if !std::path::Path::new(&clang_path).exists() {
-            return Err(anyhow::anyhow!(
-                "Android NDK tool not found: {}. Please check your ANDROID_NDK_HOME setting.", 
-                clang_path
-            ));
+    /// Environment variables pointing the build of `arch` to the NDK toolchain
+    ///
+    /// They are only set on the cargo command of this target, so building several targets in one run
+    /// never leaks the compiler of one target into the build of another.
+    fn toolchain_environment(
+        &self,
+        ndk_home: &Path,
+        target_dir: &Utf8Path,
+        arch: &str,
+    ) -> Vec<(String, OsString)> {
+        let clang = clang_path(ndk_home, arch, self.api_level);
+        let ar = ar_path(ndk_home);
+        let target_var = arch.replace('-', "_");
+        let cargo_target_var = target_var.to_uppercase();
+
+        let mut environment = vec![
+            (format!("CARGO_TARGET_{cargo_target_var}_LINKER"), clang.clone().into()),
+            (format!("CARGO_TARGET_{cargo_target_var}_AR"), ar.clone().into()),
+            (format!("CC_{target_var}"), clang.into()),
+            (format!("AR_{target_var}"), ar.into()),
+        ];
+
+        // Build scripts looking up the compiler by its unversioned name (e.g. ring) find the wrappers
+        let wrapper_dir = std::path::absolute(wrapper_dir(target_dir, arch))
+            .unwrap_or_else(|_| wrapper_dir(target_dir, arch).into_std_path_buf());
+        let paths = std::env::var_os("PATH").unwrap_or_default();
+        let paths = std::iter::once(wrapper_dir).chain(std::env::split_paths(&paths));
+        if let Ok(path) = std::env::join_paths(paths) {
+            environment.push(("PATH".to_string(), path));
         }
-        
-        // ====== SPECIAL HANDLING FOR RING CRATE ======
-        // The ring crate expects a compiler named exactly aarch64-linux-android-clang (without API level)
-        self.setup_ring_specific_fixes(&toolchain_path, path_sep, exe_ext, api)?;
-        
-        Ok(())
+
+        environment
     }
-    
-    /// Special fixes for the ring crate which needs specific compiler naming
-    fn setup_ring_specific_fixes(&self, toolchain_path: &str, path_sep: &str, exe_ext: &str, api: u32) -> Result<(), anyhow::Error> {
-        println!("Applying ring-specific fixes...");
-        
-        // Create a directory for our wrapper scripts
-        let home_dir = std::env::var("HOME").unwrap_or_else(|_| ".".to_string());
-        let wrapper_dir = format!("{}{}android-toolchain-bin", home_dir, path_sep);
-        std::fs::create_dir_all(&wrapper_dir)?;
-        
-        // For each architecture, create wrapper scripts for the compiler and archiver
+
+    /// Verifies that the NDK toolchain for this target is available and creates the compiler wrappers
+    ///
+    /// The wrappers are placed in the target directory. Nothing outside of it is modified and the linker
+    /// and archiver are passed to cargo through environment variables of the build command only.
+    pub fn setup_environment(
+        &self,
+        ndk_home: &Path,
+        target_dir: &Utf8Path,
+    ) -> Result<(), anyhow::Error> {
         for arch in &self.architectures {
-            // Create the wrapper script/symlink for clang
-            let wrapper_clang_path = format!("{}{}{}{}clang{}", 
-                wrapper_dir, path_sep, arch, "-linux-android", exe_ext);
-            
-            // The actual compiler path with API level
-            let real_compiler_path = format!("{}{}bin{}{}{}-clang{}", 
-                toolchain_path, path_sep, path_sep, arch, api, exe_ext);
-            
-            // Create the wrapper script/symlink for AR (archiver)
-            let wrapper_ar_path = format!("{}{}{}{}ar{}", 
-                wrapper_dir, path_sep, arch, "-linux-android", exe_ext);
-            
-            // The actual AR path
-            let real_ar_path = format!("{}{}bin{}llvm-ar{}", 
-                toolchain_path, path_sep, path_sep, exe_ext);
-            
-            if cfg!(target_os = "windows") {
-                // On Windows we need a .bat file to redirect for clang
-                let batch_content = format!(
-                    "@echo off\r\n\"{0}\" %*\r\n",
-                    real_compiler_path.replace("\\", "\\\\")
-                );
-                std::fs::write(&wrapper_clang_path, batch_content)?;
-                
-                // On Windows we need a .bat file to redirect for AR
-                let ar_batch_content = format!(
-                    "@echo off\r\n\"{0}\" %*\r\n",
-                    real_ar_path.replace("\\", "\\\\")
-                );
-                std::fs::write(&wrapper_ar_path, ar_batch_content)?;
-            } else {
-                // On Unix we can create shell scripts
-                
-                // Create the clang wrapper script
-                let script_content = format!(
-                    "#!/bin/sh\nexec \"{}\" \"$@\"\n",
-                    real_compiler_path
-                );
-                std::fs::write(&wrapper_clang_path, script_content)?;
-                
-                // Create the AR wrapper script
-                let ar_script_content = format!(
-                    "#!/bin/sh\nexec \"{}\" \"$@\"\n",
-                    real_ar_path
-                );
-                std::fs::write(&wrapper_ar_path, ar_script_content)?;
-                
-                // Make the scripts executable
-                use std::os::unix::fs::PermissionsExt;
-                
-                let mut clang_perms = std::fs::metadata(&wrapper_clang_path)?.permissions();
-                clang_perms.set_mode(0o755);
-                std::fs::set_permissions(&wrapper_clang_path, clang_perms)?;
-                
-                let mut ar_perms = std::fs::metadata(&wrapper_ar_path)?.permissions();
-                ar_perms.set_mode(0o755);
-                std::fs::set_permissions(&wrapper_ar_path, ar_perms)?;
+            let clang = clang_path(ndk_home, arch, self.api_level);
+            let ar = ar_path(ndk_home);
+            for tool in [&clang, &ar] {
+                if !tool.exists() {
+                    return Err(anyhow::anyhow!(
+                        "Android NDK tool not found: {}. Please check your NDK installation.",
+                        tool.display()
+                    ));
+                }
             }
+
+            // ====== SPECIAL HANDLING FOR RING CRATE ======
+            // The ring crate expects a compiler named exactly aarch64-linux-android-clang (without API level)
+            let wrapper_dir = wrapper_dir(target_dir, arch);
+            std::fs::create_dir_all(&wrapper_dir)?;
+            write_wrapper(&wrapper_dir, &format!("{}-clang", clang_target(arch)), &clang)?;
+            write_wrapper(&wrapper_dir, &format!("{}-ar", clang_target(arch)), &ar)?;
         }
-        
-        // Set environment variables for ring's build script
-        std::env::set_var("PATH", format!("{}{}{}",
-            wrapper_dir, 
-            if cfg!(target_os = "windows") { ";" } else { ":" },
-            std::env::var("PATH").unwrap_or_default()
-        ));
-        
-        // Set all possible compiler environment variables that might be checked
-        let arch = self.architectures[0];
-        let wrapper_script_path = format!("{}{}{}{}clang", 
-            wrapper_dir, path_sep, arch, "-linux-android");
-        
-        std::env::set_var("CC", &wrapper_script_path);
-        std::env::set_var(&format!("CC_{}", arch.replace("-", "_")), &wrapper_script_path);
-        std::env::set_var("TARGET_CC", &wrapper_script_path);
-        
-        // Also set AR variables
-        let ar_wrapper_path = format!("{}{}{}{}ar", 
-            wrapper_dir, path_sep, arch, "-linux-android");
-            
-        std::env::set_var("AR", &ar_wrapper_path);
-        std::env::set_var(&format!("AR_{}", arch.replace("-", "_")), &ar_wrapper_path);
-        std::env::set_var("TARGET_AR", &ar_wrapper_path);
-        
-        // Also set CFLAGS variables to help system detection
-        let cflags = format!("--target={}{}", arch, api);
-        std::env::set_var(&format!("CFLAGS_{}", arch.replace("-", "_")), &cflags);
-        std::env::set_var("TARGET_CFLAGS", &cflags);
-        
-        println!("Ring-specific fixes applied successfully.");
-        println!("Wrapper scripts created in: {}", wrapper_dir);
-        println!("Compiler wrapper: {}", wrapper_script_path);
-        println!("Archiver wrapper: {}", ar_wrapper_path);
-        
-        Ok(())
-    }
 
-    /// Generates all commands necessary to build this target
-    ///
-    /// This function returns a list of commands that should be executed in their given
-    /// order to build this target.
-    pub fn commands(
-        &self,
-        _lib_name: &str,
-        mode: Mode,
-        _lib_type: LibType,
-        features: &FeatureOptions,
-    ) -> Vec<Command> {
-        let mut commands = self.setup_commands();
-        commands.extend(self.cargo_build_commands(mode, features));
-        commands
+        Ok(())
     }
 
     /// Returns the names of all target architectures for this target
@@ -361,25 +201,74 @@ impl AndroidTarget {
         self.display_name
     }
 
-    pub fn library_directory(&self, mode: Mode) -> String {
+    pub fn library_directory(&self, target_dir: &Utf8Path, mode: Mode) -> String {
         let mode_str = match mode {
             Mode::Debug => "debug",
             Mode::Release => "release",
         };
 
-        let target = metadata().target_dir();
-        format!("{target}/{}/{mode_str}", self.architectures[0])
+        format!("{target_dir}/{}/{mode_str}", self.architectures[0])
     }
 
-    pub fn library_path(&self, lib_name: &str, mode: Mode, lib_type: LibType) -> String {
+    pub fn library_path(
+        &self,
+        target_dir: &Utf8Path,
+        lib_name: &str,
+        mode: Mode,
+        lib_type: LibType,
+    ) -> String {
         format!(
             "{}/{}",
-            self.library_directory(mode),
+            self.library_directory(target_dir, mode),
             library_file_name(lib_name, lib_type)
         )
     }
+
+    /// Returns the path of the post-processed dynamic library that is packaged into the AAR
+    ///
+    /// In release mode, its debug info is stripped. The library built by cargo is left untouched, as
+    /// the bindings are generated from its symbol table.
+    pub fn packaged_library_path(
+        &self,
+        target_dir: &Utf8Path,
+        lib_name: &str,
+        mode: Mode,
+    ) -> Utf8PathBuf {
+        target_dir
+            .join("rusify")
+            .join("android")
+            .join(self.architectures[0])
+            .join(mode.to_string())
+            .join(library_file_name(lib_name, LibType::Dynamic))
+    }
 }
 
 pub fn library_file_name(lib_name: &str, lib_type: LibType) -> String {
     format!("lib{}.{}", lib_name, lib_type.file_extension_android())
-}
\ No newline at end of file
+}
+
+/// Directory the compiler wrappers for `arch` are written to
+fn wrapper_dir(target_dir: &Utf8Path, arch: &str) -> Utf8PathBuf {
+    target_dir.join("rusify").join("android-toolchain").join(arch)
+}
+
+/// Writes an executable script named `name` to `dir` forwarding all arguments to `tool`
+fn write_wrapper(dir: &Utf8Path, name: &str, tool: &Path) -> Result<(), anyhow::Error> {
+    if cfg!(target_os = "windows") {
+        std::fs::write(
+            dir.join(format!("{name}.cmd")),
+            format!("@echo off\r\n\"{}\" %*\r\n", tool.display()),
+        )?;
+    } else {
+        let wrapper = dir.join(name);
+        std::fs::write(&wrapper, format!("#!/bin/sh\nexec \"{}\" \"$@\"\n", tool.display()))?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o755))?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,360 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use goblin::elf::program_header::PT_LOAD;
+use goblin::elf::sym::{STB_GLOBAL, STB_WEAK};
+use goblin::elf::Elf;
+
+use crate::android::ndk::Ndk;
+use crate::Result;
+
+/// Shared libraries a native library needs at runtime besides the ones provided by Android
+#[derive(Debug, Default)]
+pub(crate) struct RuntimeDependencies {
+    /// Libraries found in the NDK that have to be shipped next to the library
+    pub(crate) resolved: Vec<PathBuf>,
+    /// Names of libraries that could neither be found in the system nor in the NDK
+    pub(crate) unresolved: Vec<String>,
+}
+
+/// Page size of Android devices with 16 KB pages, which Google Play requires 64-bit libraries to support
+pub(crate) const PAGE_SIZE_16K: u64 = 16 * 1024;
+
+/// Reads and parses the ELF file at `path` and passes it to `read`
+fn with_elf<T>(path: &Path, read: impl FnOnce(&Elf) -> T) -> Result<T> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    let elf = Elf::parse(&bytes)
+        .map_err(|e| anyhow!("{} is not a valid ELF file: {e}", path.display()))?;
+
+    Ok(read(&elf))
+}
+
+/// Returns the names of the libraries listed as `DT_NEEDED` in the given ELF file
+pub(crate) fn needed_libraries(path: &Path) -> Result<Vec<String>> {
+    with_elf(path, |elf| elf.libraries.iter().map(|lib| lib.to_string()).collect())
+}
+
+/// Returns the names of all symbols the given shared library exports
+pub(crate) fn exported_symbols(path: &Path) -> Result<BTreeSet<String>> {
+    with_elf(path, |elf| {
+        elf.dynsyms
+            .iter()
+            .filter(|sym| !sym.is_import() && matches!(sym.st_bind(), STB_GLOBAL | STB_WEAK))
+            .filter_map(|sym| elf.dynstrtab.get_at(sym.st_name))
+            .map(str::to_string)
+            .collect()
+    })
+}
+
+/// Returns the index and alignment of all `LOAD` segments aligned to less than 16 KB
+///
+/// Only 64-bit libraries are checked, 32-bit ABIs are not supported on devices with 16 KB pages.
+pub(crate) fn misaligned_load_segments(path: &Path) -> Result<Vec<(usize, u64)>> {
+    with_elf(path, |elf| {
+        if !elf.is_64 {
+            return vec![];
+        }
+
+        elf.program_headers
+            .iter()
+            .filter(|header| header.p_type == PT_LOAD)
+            .enumerate()
+            .filter(|(_, header)| header.p_align < PAGE_SIZE_16K)
+            .map(|(index, header)| (index, header.p_align))
+            .collect()
+    })
+}
+
+/// Collects the runtime dependencies of `library` that are not part of the Android system
+///
+/// Libraries with a stub for the given API level in the NDK sysroot are provided by the system. All
+/// others are looked up in the sysroot of the target (e.g. `libc++_shared.so`) and next to the
+/// library itself. Dependencies of resolved libraries are followed as well.
+pub(crate) fn runtime_dependencies(
+    ndk: &Ndk,
+    rust_target: &str,
+    api_level: u32,
+    library: &Path,
+) -> Result<RuntimeDependencies> {
+    let sysroot_lib_dir = ndk.sysroot_lib_dir(rust_target);
+    let system_dir = sysroot_lib_dir.join(api_level.to_string());
+    let search_dirs = [
+        sysroot_lib_dir.clone(),
+        library.parent().unwrap_or(Path::new(".")).to_path_buf(),
+    ];
+
+    let mut dependencies = RuntimeDependencies::default();
+    // The library itself may be needed by one of its dependencies
+    let mut seen: BTreeSet<_> = library
+        .file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_string)
+        .into_iter()
+        .collect();
+    let mut pending = VecDeque::from([library.to_path_buf()]);
+
+    while let Some(path) = pending.pop_front() {
+        for name in needed_libraries(&path)? {
+            if !seen.insert(name.clone()) || system_dir.join(&name).exists() {
+                continue;
+            }
+
+            match search_dirs
+                .iter()
+                .map(|dir| dir.join(&name))
+                .find(|p| p.is_file())
+            {
+                Some(resolved) => {
+                    dependencies.resolved.push(resolved.clone());
+                    pending.push_back(resolved);
+                }
+                None => dependencies.unresolved.push(name),
+            }
+        }
+    }
+
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use goblin::elf::dynamic::{
+        DT_HASH, DT_NEEDED, DT_NULL, DT_STRSZ, DT_STRTAB, DT_SYMENT, DT_SYMTAB,
+    };
+    use goblin::elf::program_header::PT_DYNAMIC;
+    use goblin::elf::sym::STT_FUNC;
+
+    /// Builds a 64-bit little endian shared library for tests
+    ///
+    /// The first `LOAD` segment maps the whole file, further ones are empty. The library depends on
+    /// `needed` and exports a function for each name in `exports`.
+    pub(crate) fn shared_library(
+        load_aligns: &[u64],
+        needed: &[&str],
+        exports: &[&str],
+    ) -> Vec<u8> {
+        let mut dynstr = vec![0u8];
+        let mut add_string = |name: &str| {
+            let offset = dynstr.len() as u64;
+            dynstr.extend(name.as_bytes());
+            dynstr.push(0);
+            offset
+        };
+        let needed: Vec<_> = needed.iter().map(|name| add_string(name)).collect();
+        let exports: Vec<_> = exports.iter().map(|name| add_string(name)).collect();
+
+        let phnum = load_aligns.len() + 1;
+        let dynstr_offset = 64 + 56 * phnum as u64;
+        let hash_offset = (dynstr_offset + dynstr.len() as u64).next_multiple_of(8);
+        let symbol_count = exports.len() as u64 + 1;
+        let dynsym_offset = hash_offset + 8 + 4 * (1 + symbol_count);
+        let dynamic_offset = (dynsym_offset + 24 * symbol_count).next_multiple_of(8);
+        let mut dynamic: Vec<(u64, u64)> = needed.iter().map(|&name| (DT_NEEDED, name)).collect();
+        dynamic.extend([
+            (DT_HASH, hash_offset),
+            (DT_STRTAB, dynstr_offset),
+            (DT_STRSZ, dynstr.len() as u64),
+            (DT_SYMTAB, dynsym_offset),
+            (DT_SYMENT, 24),
+            (DT_NULL, 0),
+        ]);
+        let size = dynamic_offset + 16 * dynamic.len() as u64;
+
+        let mut bytes = b"\x7fELF\x02\x01\x01".to_vec();
+        bytes.resize(16, 0);
+        bytes.extend(3u16.to_le_bytes()); // ET_DYN
+        bytes.extend(183u16.to_le_bytes()); // EM_AARCH64
+        bytes.extend(1u32.to_le_bytes());
+        bytes.extend(0u64.to_le_bytes()); // e_entry
+        bytes.extend(64u64.to_le_bytes()); // e_phoff
+        bytes.extend(0u64.to_le_bytes()); // e_shoff
+        bytes.extend(0u32.to_le_bytes()); // e_flags
+        for half in [64u16, 56, phnum as u16, 64, 0, 0] {
+            bytes.extend(half.to_le_bytes());
+        }
+
+        let mut program_header = |p_type: u32, offset: u64, vaddr: u64, filesz: u64, align: u64| {
+            bytes.extend(p_type.to_le_bytes());
+            bytes.extend(4u32.to_le_bytes()); // PF_R
+            for word in [offset, vaddr, vaddr, filesz, filesz, align] {
+                bytes.extend(word.to_le_bytes());
+            }
+        };
+        for (index, &align) in load_aligns.iter().enumerate() {
+            match index {
+                0 => program_header(PT_LOAD, 0, 0, size, align),
+                _ => program_header(PT_LOAD, 0, 0x100000 * index as u64, 0, align),
+            }
+        }
+        program_header(
+            PT_DYNAMIC,
+            dynamic_offset,
+            dynamic_offset,
+            16 * dynamic.len() as u64,
+            8,
+        );
+
+        bytes.extend(&dynstr);
+        bytes.resize(hash_offset as usize, 0);
+        // One empty bucket, the chain length is the number of symbols
+        for word in [1, symbol_count as u32, 0] {
+            bytes.extend(word.to_le_bytes());
+        }
+        bytes.resize(dynsym_offset as usize + 24, 0);
+        for name in exports {
+            bytes.extend((name as u32).to_le_bytes());
+            bytes.push((STB_GLOBAL << 4) | STT_FUNC);
+            bytes.push(0);
+            bytes.extend(1u16.to_le_bytes()); // st_shndx
+            bytes.extend(0x1000u64.to_le_bytes()); // st_value
+            bytes.extend(0u64.to_le_bytes()); // st_size
+        }
+        bytes.resize(dynamic_offset as usize, 0);
+        for (tag, value) in dynamic {
+            bytes.extend(tag.to_le_bytes());
+            bytes.extend(value.to_le_bytes());
+        }
+
+        bytes
+    }
+
+    /// Builds a 32-bit little endian shared library with the given `LOAD` segments
+    fn shared_library_32(load_aligns: &[u64]) -> Vec<u8> {
+        let mut bytes = b"\x7fELF\x01\x01\x01".to_vec();
+        bytes.resize(16, 0);
+        bytes.extend(3u16.to_le_bytes()); // ET_DYN
+        bytes.extend(40u16.to_le_bytes()); // EM_ARM
+        for word in [1u32, 0, 52, 0, 0] {
+            bytes.extend(word.to_le_bytes());
+        }
+        for half in [52u16, 32, load_aligns.len() as u16, 40, 0, 0] {
+            bytes.extend(half.to_le_bytes());
+        }
+
+        let size = 52 + 32 * load_aligns.len() as u32;
+        for &align in load_aligns {
+            for word in [PT_LOAD, 0, 0, 0, size, size, 4, align as u32] {
+                bytes.extend(word.to_le_bytes());
+            }
+        }
+
+        bytes
+    }
+
+    fn write(dir: &Path, name: &str, bytes: &[u8]) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn small_load_alignments_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let aligned = write(
+            dir.path(),
+            "aligned.so",
+            &shared_library(&[0x4000, 0x10000], &[], &[]),
+        );
+        let misaligned = write(
+            dir.path(),
+            "misaligned.so",
+            &shared_library(&[0x4000, 0x1000], &[], &[]),
+        );
+
+        assert!(misaligned_load_segments(&aligned).unwrap().is_empty());
+        assert_eq!(
+            misaligned_load_segments(&misaligned).unwrap(),
+            vec![(1, 0x1000)]
+        );
+    }
+
+    #[test]
+    fn libraries_for_32_bit_abis_are_not_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = write(
+            dir.path(),
+            "libarm.so",
+            &shared_library_32(&[0x1000, 0x1000]),
+        );
+
+        assert!(misaligned_load_segments(&library).unwrap().is_empty());
+    }
+
+    #[test]
+    fn needed_libraries_and_exported_symbols_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = write(
+            dir.path(),
+            "libdemo.so",
+            &shared_library(
+                &[0x4000],
+                &["libc.so", "libc++_shared.so"],
+                &["uniffi_demo_fn_add", "ffi_demo_rustbuffer_free"],
+            ),
+        );
+
+        assert_eq!(
+            needed_libraries(&library).unwrap(),
+            vec!["libc.so", "libc++_shared.so"]
+        );
+        assert_eq!(
+            exported_symbols(&library)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["ffi_demo_rustbuffer_free", "uniffi_demo_fn_add"]
+        );
+    }
+
+    #[test]
+    fn runtime_dependencies_are_collected_transitively() {
+        let dir = tempfile::tempdir().unwrap();
+        let ndk_path = dir.path().join("ndk");
+        fs::create_dir_all(&ndk_path).unwrap();
+        fs::write(ndk_path.join("source.properties"), "Pkg.Revision = 27.0.12077973\n").unwrap();
+        let ndk = Ndk::at(&ndk_path).unwrap();
+
+        let sysroot = ndk.sysroot_lib_dir("aarch64-linux-android");
+        let system = sysroot.join("24");
+        fs::create_dir_all(&system).unwrap();
+        for name in ["libc.so", "libm.so", "libdl.so", "liblog.so"] {
+            write(&system, name, &shared_library(&[0x4000], &[], &[]));
+        }
+        // libc++_shared.so and libhelper.so need each other
+        let cpp = write(
+            &sysroot,
+            "libc++_shared.so",
+            &shared_library(&[0x4000], &["libc.so", "libm.so", "libhelper.so"], &[]),
+        );
+
+        let lib_dir = dir.path().join("lib");
+        fs::create_dir_all(&lib_dir).unwrap();
+        let helper = write(
+            &lib_dir,
+            "libhelper.so",
+            &shared_library(&[0x4000], &["libdl.so", "libc++_shared.so", "libdemo.so", "libmissing.so"], &[]),
+        );
+        let library = write(
+            &lib_dir,
+            "libdemo.so",
+            &shared_library(&[0x4000], &["libc.so", "liblog.so", "libc++_shared.so", "libhelper.so"], &[]),
+        );
+
+        let dependencies = runtime_dependencies(&ndk, "aarch64-linux-android", 24, &library).unwrap();
+        assert_eq!(dependencies.resolved, vec![cpp, helper]);
+        assert_eq!(dependencies.unresolved, vec!["libmissing.so"]);
+    }
+
+    #[test]
+    fn invalid_files_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let library = write(dir.path(), "libdemo.so", b"not an elf file");
+
+        let error = exported_symbols(&library).unwrap_err().to_string();
+        assert!(error.contains("is not a valid ELF file"), "{error}");
+    }
+}
@@ -0,0 +1,251 @@
+use anyhow::{Context, Result};
+use askama::Template;
+use camino::Utf8Path;
+use std::fs::{self, create_dir_all};
+use std::path::Path;
+
+use crate::android::aar::{copy_native_libraries, warn_unresolved_dependencies};
+use crate::android::android_target::AndroidTarget;
+use crate::android::manifest::ManifestSettings;
+use crate::android::ndk::Ndk;
+use crate::common::models::{Config, LibType, Mode};
+use crate::common::path::{copy_dir, recreate_dir};
+use crate::common::templating::{GradleBuildScript, KmpBuildScript};
+use crate::console::{messages::info, step::run_step};
+use crate::jvm::jar::copy_jvm_libraries;
+use crate::jvm::jvm_target::JvmTarget;
+
+/// Version of JNA the generated Kotlin bindings are declared to depend on
+pub(crate) const JNA_VERSION: &str = "5.14.0";
+
+/// Version of kotlinx-coroutines the bindings of async functions are declared to depend on
+pub(crate) const COROUTINES_VERSION: &str = "1.8.1";
+
+/// ProGuard/R8 rules applied to apps using the module
+const CONSUMER_RULES_FILE: &str = "consumer-rules.pro";
+
+/// Lowest SDK the module is compiled against, newer API levels raise it
+const COMPILE_SDK: u32 = 34;
+
+/// Directory of the Kotlin Multiplatform module the bindings shared by Android and the JVM are written to
+const SHARED_SOURCES_DIR: &str = "src/jvmShared/kotlin";
+
+/// Writes a Gradle library module named `module_name` to the output directory
+///
+/// Unlike the AAR, the Kotlin bindings are shipped as sources and compiled by the consuming build.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_gradle_module_with_output(
+    targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_name: &str,
+    module_name: &str,
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
+    mode: Mode,
+    lib_type: LibType,
+    config: &Config,
+) -> Result<()> {
+    let unresolved = run_step(config, "Creating Gradle library module...", || {
+        let module_dir = config.output_path(module_name).into_std_path_buf();
+        recreate_dir(&module_dir)?;
+
+        create_gradle_module(
+            targets,
+            ndk,
+            target_dir,
+            lib_name,
+            package_name,
+            config.generated_dir().join("kotlin").as_std_path(),
+            &module_dir,
+            manifest,
+            proguard_rules,
+            mode,
+            lib_type,
+        )
+    })
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create Gradle module due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    warn_unresolved_dependencies(config, unresolved);
+
+    info!(
+        config,
+        "Include the module in settings.gradle.kts with include(\":{module_name}\") and project(\":{module_name}\").projectDir = file(\"{}\")",
+        config.output_path(module_name)
+    );
+
+    Ok(())
+}
+
+/// Creates the module and returns the runtime dependencies that could not be bundled
+#[allow(clippy::too_many_arguments)]
+fn create_gradle_module(
+    targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_name: &str,
+    kotlin_dir: &Path,
+    module_dir: &Path,
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
+    mode: Mode,
+    lib_type: LibType,
+) -> Result<Vec<String>> {
+    let build_script = GradleBuildScript {
+        namespace: package_name,
+        min_sdk: manifest.min_sdk,
+        compile_sdk: COMPILE_SDK.max(manifest.min_sdk),
+        consumer_rules: CONSUMER_RULES_FILE,
+        jna_version: JNA_VERSION,
+        coroutines_version: COROUTINES_VERSION,
+    };
+    fs::write(
+        module_dir.join("build.gradle.kts"),
+        build_script
+            .render()
+            .context("Failed to render build.gradle.kts template")?,
+    )
+    .context("Failed to write build.gradle.kts")?;
+
+    fs::write(module_dir.join(CONSUMER_RULES_FILE), proguard_rules)
+        .context("Failed to write the consumer ProGuard rules")?;
+
+    let main_dir = module_dir.join("src").join("main");
+    create_dir_all(&main_dir)?;
+
+    fs::write(main_dir.join("AndroidManifest.xml"), manifest.render(None)?)
+        .context("Failed to write AndroidManifest.xml")?;
+
+    // Kotlin sources in src/main/java are picked up without further configuration
+    copy_dir(kotlin_dir, &main_dir.join("java")).context("Failed to copy Kotlin bindings")?;
+
+    copy_native_libraries(
+        targets,
+        ndk,
+        target_dir,
+        lib_name,
+        mode,
+        lib_type,
+        &main_dir.join("jniLibs"),
+    )
+}
+
+/// Writes a Kotlin Multiplatform module named `module_name` to the output directory
+///
+/// The Kotlin bindings are added to the androidMain and jvmMain source sets. The Android libraries
+/// are packaged as jniLibs and the libraries of `jvm_targets` as JNA resources of jvmMain.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_kmp_module_with_output(
+    targets: &[AndroidTarget],
+    jvm_targets: &[JvmTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_name: &str,
+    module_name: &str,
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
+    mode: Mode,
+    config: &Config,
+) -> Result<()> {
+    let unresolved = run_step(config, "Creating Kotlin Multiplatform module...", || {
+        let module_dir = config.output_path(module_name).into_std_path_buf();
+        recreate_dir(&module_dir)?;
+
+        create_kmp_module(
+            targets,
+            jvm_targets,
+            ndk,
+            target_dir,
+            lib_name,
+            package_name,
+            config.generated_dir().join("kotlin").as_std_path(),
+            &module_dir,
+            manifest,
+            proguard_rules,
+            mode,
+        )
+    })
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create Kotlin Multiplatform module due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    warn_unresolved_dependencies(config, unresolved);
+
+    info!(
+        config,
+        "Include the module in settings.gradle.kts with include(\":{module_name}\") and project(\":{module_name}\").projectDir = file(\"{}\")",
+        config.output_path(module_name)
+    );
+
+    Ok(())
+}
+
+/// Creates the module and returns the runtime dependencies that could not be bundled
+#[allow(clippy::too_many_arguments)]
+fn create_kmp_module(
+    targets: &[AndroidTarget],
+    jvm_targets: &[JvmTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_name: &str,
+    kotlin_dir: &Path,
+    module_dir: &Path,
+    manifest: &ManifestSettings,
+    proguard_rules: &str,
+    mode: Mode,
+) -> Result<Vec<String>> {
+    let build_script = KmpBuildScript {
+        namespace: package_name,
+        min_sdk: manifest.min_sdk,
+        compile_sdk: COMPILE_SDK.max(manifest.min_sdk),
+        consumer_rules: CONSUMER_RULES_FILE,
+        shared_sources: SHARED_SOURCES_DIR,
+        jna_version: JNA_VERSION,
+        coroutines_version: COROUTINES_VERSION,
+    };
+    fs::write(
+        module_dir.join("build.gradle.kts"),
+        build_script
+            .render()
+            .context("Failed to render build.gradle.kts template")?,
+    )
+    .context("Failed to write build.gradle.kts")?;
+
+    fs::write(module_dir.join(CONSUMER_RULES_FILE), proguard_rules)
+        .context("Failed to write the consumer ProGuard rules")?;
+
+    copy_dir(kotlin_dir, &module_dir.join(SHARED_SOURCES_DIR))
+        .context("Failed to copy Kotlin bindings")?;
+
+    let jvm_resources_dir = module_dir.join("src").join("jvmMain").join("resources");
+    create_dir_all(&jvm_resources_dir)?;
+    copy_jvm_libraries(jvm_targets, target_dir, lib_name, mode, &jvm_resources_dir)?;
+
+    let android_dir = module_dir.join("src").join("androidMain");
+    create_dir_all(&android_dir)?;
+
+    fs::write(android_dir.join("AndroidManifest.xml"), manifest.render(None)?)
+        .context("Failed to write AndroidManifest.xml")?;
+
+    copy_native_libraries(
+        targets,
+        ndk,
+        target_dir,
+        lib_name,
+        mode,
+        LibType::Dynamic,
+        &android_dir.join("jniLibs"),
+    )
+}
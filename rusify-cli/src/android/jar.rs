@@ -0,0 +1,263 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{Seek, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+use crate::common::models::Config;
+use crate::common::toolchain::is_installed;
+use crate::console::{messages::warning, step::run_step_with_commands};
+use crate::Result;
+
+/// Manifest written to every jar, the JVM expects it to be the first entry
+const JAR_MANIFEST: &str = "Manifest-Version: 1.0\r\nCreated-By: rusify\r\n\r\n";
+
+/// Maven coordinates of the libraries the generated Kotlin bindings are compiled against
+const KOTLIN_DEPENDENCIES: [(&str, &str); 2] = [
+    ("net.java.dev.jna", "jna"),
+    ("org.jetbrains.kotlinx", "kotlinx-coroutines-core-jvm"),
+];
+
+/// Writes all files below `dir` into the zip archive at `archive_path`
+///
+/// Entries are stored relative to `dir`. If `jar` is set, a `META-INF/MANIFEST.MF` is added first.
+pub(crate) fn zip_dir(dir: &Path, archive_path: &Path, jar: bool) -> Result<()> {
+    let (mut zip, options) = create_zip(archive_path, jar)?;
+
+    if dir.exists() {
+        add_dir_to_zip(&mut zip, &options, dir, dir, jar)
+            .with_context(|| format!("Failed to add files to {}", archive_path.display()))?;
+    }
+
+    zip.finish()
+        .with_context(|| format!("Failed to finalize {}", archive_path.display()))?;
+
+    Ok(())
+}
+
+/// Writes a jar containing only its manifest
+pub(crate) fn write_empty_jar(jar_path: &Path) -> Result<()> {
+    let (mut zip, _) = create_zip(jar_path, true)?;
+    zip.finish()
+        .with_context(|| format!("Failed to finalize {}", jar_path.display()))?;
+
+    Ok(())
+}
+
+fn create_zip(archive_path: &Path, jar: bool) -> Result<(ZipWriter<File>, FileOptions)> {
+    let file = File::create(archive_path)
+        .with_context(|| format!("Failed to create {}", archive_path.display()))?;
+
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .unix_permissions(0o755);
+
+    if jar {
+        zip.add_directory("META-INF/", options)?;
+        zip.start_file("META-INF/MANIFEST.MF", options)?;
+        zip.write_all(JAR_MANIFEST.as_bytes())?;
+    }
+
+    Ok((zip, options))
+}
+
+fn add_dir_to_zip<T: Write + Seek>(
+    zip: &mut ZipWriter<T>,
+    options: &FileOptions,
+    dir: &Path,
+    root: &Path,
+    jar: bool,
+) -> Result<()> {
+    // Sorted entries keep the archive reproducible
+    let mut entries = fs::read_dir(dir)?.collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        // Zip entries always use forward slashes
+        let relative_path = path
+            .strip_prefix(root)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        // The manifest of a jar has already been written by create_zip
+        let written = jar && matches!(relative_path.as_str(), "META-INF" | "META-INF/MANIFEST.MF");
+
+        if path.is_dir() {
+            if !written {
+                zip.add_directory(format!("{relative_path}/"), *options)?;
+            }
+            add_dir_to_zip(zip, options, &path, root, jar)?;
+        } else if !written {
+            zip.start_file(relative_path, *options)?;
+            let mut file = File::open(&path)?;
+            std::io::copy(&mut file, zip)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the jars the Kotlin bindings are compiled against
+///
+/// The configured jars come first. JNA and kotlinx-coroutines are looked up in the Gradle cache if
+/// they are not configured. Returns an error naming the libraries that could not be found.
+pub(crate) fn kotlin_classpath(configured: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut classpath = configured.to_vec();
+    let mut missing = vec![];
+
+    for (group, artifact) in KOTLIN_DEPENDENCIES {
+        let configured = configured.iter().any(|jar| {
+            jar.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| is_artifact_jar(name, artifact))
+        });
+        if configured {
+            continue;
+        }
+
+        match find_in_gradle_cache(group, artifact) {
+            Some(jar) => classpath.push(jar),
+            None => missing.push(format!("{group}:{artifact}")),
+        }
+    }
+
+    if missing.is_empty() {
+        Ok(classpath)
+    } else {
+        Err(anyhow!(
+            "Could not find {} in the Gradle cache. Add the jars to kotlin-classpath in the [android] or [jvm] section of rusify.toml.",
+            missing.join(", ")
+        ))
+    }
+}
+
+/// Whether `file_name` is the binary jar of `artifact`, e.g. `jna-5.14.0.jar` but not `jna-platform-5.14.0.jar`
+fn is_artifact_jar(file_name: &str, artifact: &str) -> bool {
+    file_name
+        .strip_prefix(artifact)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(".jar"))
+        .is_some_and(|version| {
+            version.starts_with(|c: char| c.is_ascii_digit())
+                && !version.ends_with("-sources")
+                && !version.ends_with("-javadoc")
+        })
+}
+
+/// Returns the newest jar of the given artifact downloaded by Gradle
+fn find_in_gradle_cache(group: &str, artifact: &str) -> Option<PathBuf> {
+    let gradle_home = std::env::var_os("GRADLE_USER_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .or_else(|| std::env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".gradle"))
+        })?;
+
+    let pattern = gradle_home
+        .join("caches")
+        .join("modules-2")
+        .join("files-2.1")
+        .join(group)
+        .join(artifact)
+        .join("*")
+        .join("*")
+        .join(format!("{artifact}-*.jar"));
+
+    glob::glob(pattern.to_str()?)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| is_artifact_jar(name, artifact))
+        })
+        .max_by_key(|jar| artifact_version(jar))
+}
+
+/// Returns the numeric components of the version directory a cached jar is stored in
+///
+/// The Gradle cache layout is `<group>/<artifact>/<version>/<sha1>/<artifact>-<version>.jar`.
+fn artifact_version(jar: &Path) -> Vec<u32> {
+    jar.ancestors()
+        .nth(2)
+        .and_then(|dir| dir.file_name())
+        .and_then(|name| name.to_str())
+        .map(|version| {
+            version
+                .split(['.', '-'])
+                .map_while(|component| component.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the command compiling the Kotlin sources in `sources_dir` into `jar_path` with kotlinc
+///
+/// kotlinc writes a jar if the path ends with `.jar` and a directory of class files otherwise.
+pub(crate) fn kotlinc_command(sources_dir: &Path, jar_path: &Path, classpath: &[PathBuf]) -> Result<Command> {
+    let classpath: OsString = std::env::join_paths(classpath)
+        .context("The Kotlin classpath contains an invalid path")?;
+
+    let mut command = Command::new("kotlinc");
+    command
+        .arg(sources_dir)
+        .arg("-classpath")
+        .arg(classpath)
+        .args(["-jvm-target", "1.8", "-no-reflect", "-nowarn", "-d"])
+        .arg(jar_path);
+
+    Ok(command)
+}
+
+/// Compiles the generated Kotlin bindings in `sources_dir` into `destination`, a jar or a directory
+///
+/// Returns `false` without failing if kotlinc or the libraries the bindings depend on are not
+/// available, in which case the package only contains the native libraries.
+pub(crate) fn compile_kotlin_with_output(
+    sources_dir: &Path,
+    destination: &Path,
+    configured_classpath: &[PathBuf],
+    config: &Config,
+) -> Result<bool> {
+    if !is_installed("kotlinc", "-version") {
+        warning!(
+            config,
+            "kotlinc was not found, the package will not contain the compiled Kotlin bindings. Install the Kotlin compiler or add the sources jar to your app."
+        );
+        return Ok(false);
+    }
+
+    let classpath = match kotlin_classpath(configured_classpath) {
+        Ok(classpath) => classpath,
+        Err(e) => {
+            warning!(
+                config,
+                "{e} The package will not contain the compiled Kotlin bindings."
+            );
+            return Ok(false);
+        }
+    };
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if destination.is_dir() {
+        fs::remove_dir_all(destination)?;
+    } else if destination.exists() {
+        fs::remove_file(destination)?;
+    }
+
+    let kotlinc = kotlinc_command(sources_dir, destination, &classpath)?;
+    run_step_with_commands(config, "Compiling Kotlin bindings...", &mut [kotlinc])?;
+
+    Ok(true)
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Context, Result};
+use askama::Template;
+
+use crate::common::templating::AndroidManifest;
+
+/// Prefix of the permissions defined by the Android platform
+const PLATFORM_PERMISSION_PREFIX: &str = "android.permission.";
+
+/// Settings rendered into the AndroidManifest.xml of the AAR or Gradle module
+#[derive(Debug, Clone)]
+pub(crate) struct ManifestSettings {
+    pub(crate) min_sdk: u32,
+    pub(crate) permissions: Vec<String>,
+    pub(crate) extract_native_libs: Option<bool>,
+}
+
+impl ManifestSettings {
+    /// Validates the configured permissions
+    ///
+    /// Permissions without a dot are platform permissions, e.g. `INTERNET` becomes
+    /// `android.permission.INTERNET`.
+    pub(crate) fn new(
+        min_sdk: u32,
+        permissions: &[String],
+        extract_native_libs: Option<bool>,
+    ) -> Result<Self> {
+        let mut qualified: Vec<String> = vec![];
+        for permission in permissions {
+            let permission = if permission.contains('.') {
+                permission.clone()
+            } else {
+                format!("{PLATFORM_PERMISSION_PREFIX}{permission}")
+            };
+
+            if !is_valid_permission(&permission) {
+                return Err(anyhow!(
+                    "Invalid Android permission \"{permission}\"! Permissions are dot-separated Java identifiers like android.permission.INTERNET."
+                ));
+            }
+            if !qualified.contains(&permission) {
+                qualified.push(permission);
+            }
+        }
+
+        Ok(Self {
+            min_sdk,
+            permissions: qualified,
+            extract_native_libs,
+        })
+    }
+
+    /// Renders the manifest
+    ///
+    /// `package_name` is only passed for AARs. Gradle modules declare the namespace and minSdk in
+    /// build.gradle.kts, where the Android Gradle Plugin expects them.
+    pub(crate) fn render(&self, package_name: Option<&str>) -> Result<String> {
+        AndroidManifest {
+            package_name,
+            min_sdk: package_name.map(|_| self.min_sdk),
+            permissions: &self.permissions,
+            extract_native_libs: self.extract_native_libs,
+        }
+        .render()
+        .context("Failed to render AndroidManifest.xml template")
+    }
+}
+
+/// Whether `permission` is a valid `android:name` of a `<uses-permission>` element
+fn is_valid_permission(permission: &str) -> bool {
+    permission.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ANDROID_NS: &str = "http://schemas.android.com/apk/res/android";
+
+    fn permissions(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn renders_well_formed_aar_manifest() {
+        let settings = ManifestSettings::new(
+            24,
+            &permissions(&["INTERNET", "android.permission.CAMERA", "com.example.permission.SYNC"]),
+            Some(false),
+        )
+        .unwrap();
+        let manifest = settings.render(Some("com.example.demo")).unwrap();
+
+        let document = roxmltree::Document::parse(&manifest).unwrap();
+        let root = document.root_element();
+        assert_eq!(root.tag_name().name(), "manifest");
+        assert_eq!(root.attribute("package"), Some("com.example.demo"));
+
+        let uses_sdk = root.children().find(|node| node.has_tag_name("uses-sdk")).unwrap();
+        assert_eq!(uses_sdk.attribute((ANDROID_NS, "minSdkVersion")), Some("24"));
+
+        let permissions: Vec<_> = root
+            .children()
+            .filter(|node| node.has_tag_name("uses-permission"))
+            .filter_map(|node| node.attribute((ANDROID_NS, "name")))
+            .collect();
+        assert_eq!(
+            permissions,
+            [
+                "android.permission.INTERNET",
+                "android.permission.CAMERA",
+                "com.example.permission.SYNC"
+            ]
+        );
+
+        let application = root.children().find(|node| node.has_tag_name("application")).unwrap();
+        assert_eq!(application.attribute((ANDROID_NS, "extractNativeLibs")), Some("false"));
+    }
+
+    #[test]
+    fn gradle_module_manifest_omits_package_and_min_sdk() {
+        let settings = ManifestSettings::new(21, &[], None).unwrap();
+        let manifest = settings.render(None).unwrap();
+
+        let document = roxmltree::Document::parse(&manifest).unwrap();
+        let root = document.root_element();
+        assert_eq!(root.attribute("package"), None);
+        assert!(!root.children().any(|node| node.has_tag_name("uses-sdk")));
+        assert!(!root.children().any(|node| node.has_tag_name("uses-permission")));
+
+        let application = root.children().find(|node| node.has_tag_name("application")).unwrap();
+        assert_eq!(application.attribute((ANDROID_NS, "extractNativeLibs")), None);
+    }
+
+    #[test]
+    fn duplicate_permissions_are_removed() {
+        let settings =
+            ManifestSettings::new(21, &permissions(&["INTERNET", "android.permission.INTERNET"]), None)
+                .unwrap();
+        assert_eq!(settings.permissions, ["android.permission.INTERNET"]);
+    }
+
+    #[test]
+    fn rejects_malformed_permissions() {
+        for permission in ["", "1NTERNET", "android..INTERNET", "android.permission.", "INTER NET", "a\"/><x"] {
+            assert!(
+                ManifestSettings::new(21, &permissions(&[permission]), None).is_err(),
+                "{permission}"
+            );
+        }
+    }
+}
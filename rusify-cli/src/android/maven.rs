@@ -0,0 +1,302 @@
+use anyhow::{anyhow, Context, Result};
+use askama::Template;
+use camino::{Utf8Path, Utf8PathBuf};
+use md5::Md5;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::fs::{self, create_dir_all};
+use std::path::Path;
+
+use crate::android::gradle::{COROUTINES_VERSION, JNA_VERSION};
+use crate::common::models::Config;
+use crate::common::templating::{MavenMetadataXml, PomXml};
+use crate::console::{messages::info, step::run_step};
+
+/// Maven coordinates an AAR is published with
+#[derive(Debug, Clone)]
+pub(crate) struct MavenCoordinates {
+    pub(crate) group_id: String,
+    pub(crate) artifact_id: String,
+    pub(crate) version: String,
+}
+
+impl MavenCoordinates {
+    /// Verifies that the coordinates can be used as directory and file names in the repository
+    pub(crate) fn validate(&self) -> Result<()> {
+        for (name, value) in [
+            ("group id", &self.group_id),
+            ("artifact id", &self.artifact_id),
+            ("version", &self.version),
+        ] {
+            let invalid = value.is_empty()
+                || value
+                    .chars()
+                    .any(|c| c.is_whitespace() || matches!(c, '/' | '\\' | ':'));
+            if invalid {
+                return Err(anyhow!(
+                    "Invalid Maven {name} \"{value}\"! It must not be empty or contain whitespace, slashes or colons."
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for MavenCoordinates {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.group_id, self.artifact_id, self.version)
+    }
+}
+
+/// Publishes the AAR and its sources jar to the Maven repository directory `repo`
+pub(crate) fn publish_aar_with_output(
+    repo: &Utf8Path,
+    coordinates: &MavenCoordinates,
+    name: &str,
+    description: Option<&str>,
+    aar_path: &Path,
+    sources_jar: &Path,
+    config: &Config,
+) -> Result<()> {
+    run_step(config, format!("Publishing {coordinates} to {repo}..."), || {
+        publish_aar(repo, coordinates, name, description, aar_path, sources_jar)
+    })
+    .map_err(|e| {
+        anyhow!(
+            "Failed to publish AAR to {repo} due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    info!(
+        config,
+        "Published {coordinates}. Add maven {{ url = uri(\"{}\") }} to the repositories of your app to use it.",
+        std::path::absolute(repo)
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| repo.to_string())
+    );
+
+    Ok(())
+}
+
+/// Writes the artifacts in the Maven 2 repository layout
+///
+/// `<group>/<artifact>/<version>/` receives the AAR, the sources jar and the POM, while
+/// `<group>/<artifact>/maven-metadata.xml` lists all published versions. Every file is accompanied
+/// by `.md5`, `.sha1` and `.sha256` checksums.
+fn publish_aar(
+    repo: &Utf8Path,
+    coordinates: &MavenCoordinates,
+    name: &str,
+    description: Option<&str>,
+    aar_path: &Path,
+    sources_jar: &Path,
+) -> Result<()> {
+    let artifact_dir = artifact_dir(repo, coordinates);
+    let version_dir = artifact_dir.join(&coordinates.version);
+    create_dir_all(&version_dir)?;
+
+    let base_name = format!("{}-{}", coordinates.artifact_id, coordinates.version);
+
+    let aar = version_dir.join(format!("{base_name}.aar"));
+    fs::copy(aar_path, &aar).context("Failed to copy the AAR")?;
+    write_checksums(&aar)?;
+
+    let sources = version_dir.join(format!("{base_name}-sources.jar"));
+    fs::copy(sources_jar, &sources).context("Failed to copy the sources jar")?;
+    write_checksums(&sources)?;
+
+    let pom = PomXml {
+        group_id: &coordinates.group_id,
+        artifact_id: &coordinates.artifact_id,
+        version: &coordinates.version,
+        name,
+        description,
+        jna_version: JNA_VERSION,
+        coroutines_version: COROUTINES_VERSION,
+    };
+    let pom_path = version_dir.join(format!("{base_name}.pom"));
+    fs::write(&pom_path, pom.render().context("Failed to render the POM template")?)
+        .context("Failed to write the POM")?;
+    write_checksums(&pom_path)?;
+
+    let metadata_path = artifact_dir.join("maven-metadata.xml");
+    fs::write(&metadata_path, render_metadata(&metadata_path, coordinates)?)
+        .context("Failed to write maven-metadata.xml")?;
+    write_checksums(&metadata_path)?;
+
+    Ok(())
+}
+
+fn artifact_dir(repo: &Utf8Path, coordinates: &MavenCoordinates) -> Utf8PathBuf {
+    coordinates
+        .group_id
+        .split('.')
+        .fold(repo.to_path_buf(), |dir, segment| dir.join(segment))
+        .join(&coordinates.artifact_id)
+}
+
+/// Renders `maven-metadata.xml` with the versions already listed in `path` and the published one
+///
+/// `latest` and `release` point to the highest listed versions, so publishing a backport does not
+/// move them backwards.
+fn render_metadata(path: &Utf8Path, coordinates: &MavenCoordinates) -> Result<String> {
+    let mut versions = fs::read_to_string(path)
+        .map(|xml| published_versions(&xml))
+        .unwrap_or_default();
+    if !versions.contains(&coordinates.version) {
+        versions.push(coordinates.version.clone());
+    }
+
+    let latest = versions
+        .iter()
+        .max_by(|a, b| compare_versions(a, b))
+        .expect("The published version is always listed");
+    let release = versions
+        .iter()
+        .filter(|version| !version.ends_with("-SNAPSHOT"))
+        .max_by(|a, b| compare_versions(a, b));
+
+    let now = time::OffsetDateTime::now_utc();
+    let last_updated = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}",
+        now.year(),
+        u8::from(now.month()),
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second()
+    );
+
+    MavenMetadataXml {
+        group_id: &coordinates.group_id,
+        artifact_id: &coordinates.artifact_id,
+        latest,
+        release: release.map(String::as_str),
+        versions: &versions,
+        last_updated: &last_updated,
+    }
+    .render()
+    .context("Failed to render the maven-metadata.xml template")
+}
+
+/// Orders Maven versions by their numeric components, e.g. `1.10.0` after `1.9.0`
+///
+/// A version with a qualifier like `2.0.0-beta1` or `2.0.0-SNAPSHOT` comes before the plain `2.0.0`.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    fn split(version: &str) -> (Vec<&str>, Option<&str>) {
+        let (numbers, qualifier) = match version.split_once('-') {
+            Some((numbers, qualifier)) => (numbers, Some(qualifier)),
+            None => (version, None),
+        };
+        (numbers.split('.').collect(), qualifier)
+    }
+
+    let (a_numbers, a_qualifier) = split(a);
+    let (b_numbers, b_qualifier) = split(b);
+
+    for i in 0..a_numbers.len().max(b_numbers.len()) {
+        let a_component = a_numbers.get(i).copied().unwrap_or("0");
+        let b_component = b_numbers.get(i).copied().unwrap_or("0");
+        let ordering = match (a_component.parse::<u64>(), b_component.parse::<u64>()) {
+            (Ok(a_number), Ok(b_number)) => a_number.cmp(&b_number),
+            _ => a_component.cmp(b_component),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    match (a_qualifier, b_qualifier) {
+        (None, None) => Ordering::Equal,
+        (None, Some(_)) => Ordering::Greater,
+        (Some(_), None) => Ordering::Less,
+        (Some(a_qualifier), Some(b_qualifier)) => a_qualifier
+            .to_ascii_lowercase()
+            .cmp(&b_qualifier.to_ascii_lowercase()),
+    }
+}
+
+/// Returns the contents of all `<version>` elements of an existing `maven-metadata.xml`
+fn published_versions(xml: &str) -> Vec<String> {
+    xml.split("<version>")
+        .skip(1)
+        .filter_map(|part| part.split_once("</version>"))
+        .map(|(version, _)| version.trim().to_string())
+        .filter(|version| !version.is_empty())
+        .collect()
+}
+
+/// Writes the checksum files Maven and Gradle verify downloads with next to `path`
+fn write_checksums(path: &Utf8Path) -> Result<()> {
+    let content = fs::read(path).with_context(|| format!("Could not read {path}"))?;
+
+    for (extension, checksum) in [
+        ("md5", format!("{:x}", Md5::digest(&content))),
+        ("sha1", format!("{:x}", Sha1::digest(&content))),
+        ("sha256", format!("{:x}", Sha256::digest(&content))),
+    ] {
+        fs::write(format!("{path}.{extension}"), checksum)
+            .with_context(|| format!("Could not write the {extension} checksum of {path}"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coordinates(version: &str) -> MavenCoordinates {
+        MavenCoordinates {
+            group_id: "com.example".to_string(),
+            artifact_id: "demo".to_string(),
+            version: version.to_string(),
+        }
+    }
+
+    /// Publishes the versions one after another and returns the final metadata
+    fn publish(versions: &[&str]) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Utf8Path::from_path(dir.path()).unwrap().join("maven-metadata.xml");
+        for version in versions {
+            let metadata = render_metadata(&path, &coordinates(version)).unwrap();
+            fs::write(&path, metadata).unwrap();
+        }
+        fs::read_to_string(&path).unwrap()
+    }
+
+    #[test]
+    fn backport_does_not_move_latest_backwards() {
+        let metadata = publish(&["1.2.0", "2.0.0", "1.2.1"]);
+
+        assert!(metadata.contains("<latest>2.0.0</latest>"), "{metadata}");
+        assert!(metadata.contains("<release>2.0.0</release>"), "{metadata}");
+        assert_eq!(published_versions(&metadata), ["1.2.0", "2.0.0", "1.2.1"]);
+    }
+
+    #[test]
+    fn snapshots_are_latest_but_not_release() {
+        let metadata = publish(&["1.0.0", "1.1.0-SNAPSHOT"]);
+
+        assert!(metadata.contains("<latest>1.1.0-SNAPSHOT</latest>"), "{metadata}");
+        assert!(metadata.contains("<release>1.0.0</release>"), "{metadata}");
+    }
+
+    #[test]
+    fn republishing_a_version_does_not_duplicate_it() {
+        let metadata = publish(&["1.0.0", "1.0.0"]);
+        assert_eq!(published_versions(&metadata), ["1.0.0"]);
+    }
+
+    #[test]
+    fn versions_are_compared_numerically() {
+        assert_eq!(compare_versions("1.10.0", "1.9.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.2", "1.2.0"), Ordering::Equal);
+        assert_eq!(compare_versions("2.0.0-SNAPSHOT", "2.0.0"), Ordering::Less);
+        assert_eq!(compare_versions("2.0.0-beta1", "1.9.9"), Ordering::Greater);
+        assert_eq!(compare_versions("2.0.0-alpha", "2.0.0-beta"), Ordering::Less);
+    }
+}
@@ -0,0 +1,421 @@
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use itertools::Itertools;
+
+use crate::Result;
+
+/// An installed Android NDK
+#[derive(Debug, Clone)]
+pub(crate) struct Ndk {
+    pub(crate) path: PathBuf,
+    pub(crate) version: NdkVersion,
+}
+
+/// NDK version as found in `Pkg.Revision`, e.g. `26.1.10909125`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct NdkVersion(Vec<u32>);
+
+impl NdkVersion {
+    pub(crate) fn parse(version: &str) -> Option<Self> {
+        // Pre-release NDKs carry a suffix like `-beta1` after the last component
+        let version = version.trim().split('-').next()?;
+        version
+            .split('.')
+            .map(|component| component.parse().ok())
+            .collect::<Option<Vec<_>>>()
+            .filter(|components| !components.is_empty())
+            .map(Self)
+    }
+
+    /// Whether this version starts with all components of `prefix`, so that `26` matches `26.1.10909125`
+    fn matches(&self, prefix: &NdkVersion) -> bool {
+        self.0.starts_with(&prefix.0)
+    }
+}
+
+impl Display for NdkVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.iter().join("."))
+    }
+}
+
+/// Locations searched for installed NDKs
+///
+/// Explicitly configured NDK directories are preferred over the NDKs found inside of SDK directories.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NdkSearch {
+    /// NDK roots with the name of the variable that configured them, e.g. `ANDROID_NDK_HOME`
+    pub(crate) ndk_dirs: Vec<(&'static str, PathBuf)>,
+    /// SDK roots containing `ndk/<version>` or `ndk-bundle`
+    pub(crate) sdk_dirs: Vec<PathBuf>,
+}
+
+impl NdkSearch {
+    /// Collects the locations from the environment and the default SDK install locations of the host
+    pub(crate) fn from_env() -> Self {
+        let var = |name: &str| std::env::var_os(name).map(PathBuf::from);
+
+        let ndk_dirs = ["ANDROID_NDK_HOME", "ANDROID_NDK_ROOT", "ANDROID_NDK"]
+            .iter()
+            .filter_map(|name| Some((*name, var(name)?)))
+            .collect();
+
+        let home = var("HOME").or_else(|| var("USERPROFILE"));
+        let default_sdk = if cfg!(target_os = "macos") {
+            home.map(|home| home.join("Library").join("Android").join("sdk"))
+        } else if cfg!(target_os = "windows") {
+            var("LOCALAPPDATA").map(|local| local.join("Android").join("Sdk"))
+        } else {
+            home.map(|home| home.join("Android").join("Sdk"))
+        };
+
+        let sdk_dirs = ["ANDROID_HOME", "ANDROID_SDK_ROOT"]
+            .iter()
+            .filter_map(|name| var(name))
+            .chain(default_sdk)
+            .collect();
+
+        Self { ndk_dirs, sdk_dirs }
+    }
+
+    /// Returns all valid NDKs found, explicitly configured ones first and newest first otherwise
+    ///
+    /// Fails if an explicitly configured directory is not a valid NDK instead of falling back to
+    /// another one.
+    pub(crate) fn installed(&self) -> Result<Vec<Ndk>> {
+        let explicit = self
+            .ndk_dirs
+            .iter()
+            .map(|(name, dir)| {
+                if !dir.is_dir() {
+                    return Err(anyhow!("{name} is set to {}, which does not exist!", dir.display()));
+                }
+                Ndk::at(dir).ok_or_else(|| {
+                    anyhow!(
+                        "{name} is set to {}, which is not a valid Android NDK! It does not contain a source.properties with a Pkg.Revision.",
+                        dir.display()
+                    )
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let bundled = self
+            .sdk_dirs
+            .iter()
+            .flat_map(|sdk| {
+                let versions = fs::read_dir(sdk.join("ndk"))
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path());
+                versions.chain(std::iter::once(sdk.join("ndk-bundle")))
+            })
+            .filter_map(|dir| Ndk::at(&dir))
+            .sorted_by(|a, b| b.version.cmp(&a.version));
+
+        Ok(explicit
+            .into_iter()
+            .chain(bundled)
+            .unique_by(|ndk| fs::canonicalize(&ndk.path).unwrap_or_else(|_| ndk.path.clone()))
+            .collect())
+    }
+
+    /// Selects the NDK to build with
+    ///
+    /// Without a requested version, an explicitly configured NDK is used, otherwise the newest one.
+    /// A requested version like `26` or `26.1` selects the newest NDK starting with these components.
+    pub(crate) fn find(&self, version: Option<&str>) -> Result<Ndk> {
+        let installed = self.installed()?;
+
+        let Some(version) = version else {
+            return installed.into_iter().next().ok_or_else(|| {
+                anyhow!(
+                    "No Android NDK found! Please install one with the SDK manager or set ANDROID_NDK_HOME.{}",
+                    self.searched_locations()
+                )
+            });
+        };
+
+        let requested = NdkVersion::parse(version)
+            .ok_or_else(|| anyhow!("Invalid NDK version {version}, expected e.g. 26 or 26.1.10909125"))?;
+
+        installed
+            .iter()
+            .filter(|ndk| ndk.version.matches(&requested))
+            .max_by(|a, b| a.version.cmp(&b.version))
+            .cloned()
+            .ok_or_else(|| {
+                let available = installed.iter().map(|ndk| ndk.version.to_string()).join(", ");
+                anyhow!(
+                    "Android NDK {version} is not installed! Installed versions: {}",
+                    if available.is_empty() { "none" } else { &available }
+                )
+            })
+    }
+
+    fn searched_locations(&self) -> String {
+        let locations = self
+            .ndk_dirs
+            .iter()
+            .map(|(_, dir)| dir)
+            .chain(&self.sdk_dirs)
+            .map(|dir| format!("\n  {}", dir.display()))
+            .join("");
+
+        if locations.is_empty() {
+            String::new()
+        } else {
+            format!(" Searched locations:{locations}")
+        }
+    }
+}
+
+impl Ndk {
+    /// Returns the NDK at `path`, if it contains a `source.properties` with a valid version
+    pub(crate) fn at(path: &Path) -> Option<Self> {
+        let version = ndk_version(path).and_then(|version| NdkVersion::parse(&version))?;
+
+        Some(Self {
+            path: path.to_path_buf(),
+            version,
+        })
+    }
+
+    /// Returns the sysroot library directory for the given Rust target
+    ///
+    /// It contains the runtime libraries shipped with apps, e.g. `libc++_shared.so`, and one directory
+    /// per API level with the stubs of the libraries provided by the system.
+    pub(crate) fn sysroot_lib_dir(&self, rust_target: &str) -> PathBuf {
+        // The sysroot uses the unversioned GNU triple, i.e. arm instead of armv7a
+        let sysroot_target = match rust_target {
+            "armv7-linux-androideabi" => "arm-linux-androideabi",
+            target => target,
+        };
+
+        toolchain_dir(&self.path)
+            .join("sysroot")
+            .join("usr")
+            .join("lib")
+            .join(sysroot_target)
+    }
+
+    /// Returns the API levels the NDK provides libraries for when building for the given Rust target
+    pub(crate) fn api_levels(&self, rust_target: &str) -> Vec<u32> {
+        fs::read_dir(self.sysroot_lib_dir(rust_target))
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+            .sorted()
+            .collect()
+    }
+
+    /// Verifies that the NDK can build for the given Rust target with the given API level
+    pub(crate) fn validate_api_level(&self, rust_target: &str, api_level: u32) -> Result<()> {
+        let levels = self.api_levels(rust_target);
+        if levels.contains(&api_level) {
+            return Ok(());
+        }
+
+        match (levels.first(), levels.last()) {
+            (Some(min), Some(max)) => Err(anyhow!(
+                "API level {api_level} is not supported by Android NDK {} for {rust_target}. Supported API levels: {min} to {max}",
+                self.version
+            )),
+            _ => Err(anyhow!(
+                "Android NDK {} at {} does not contain a sysroot for {rust_target}",
+                self.version,
+                self.path.display()
+            )),
+        }
+    }
+}
+
+/// Returns the directory of the prebuilt LLVM toolchain inside the NDK for the current host
+pub(crate) fn toolchain_dir(ndk_home: &Path) -> PathBuf {
+    let host_os = if cfg!(target_os = "macos") {
+        "darwin"
+    } else if cfg!(target_os = "windows") {
+        "windows"
+    } else {
+        "linux"
+    };
+
+    ndk_home
+        .join("toolchains")
+        .join("llvm")
+        .join("prebuilt")
+        .join(format!("{host_os}-x86_64"))
+}
+
+/// Returns the clang driver of the NDK compiling for the given Rust target and API level
+pub(crate) fn clang_path(ndk_home: &Path, rust_target: &str, api_level: u32) -> PathBuf {
+    let clang_target = clang_target(rust_target);
+    // On Windows, the target specific drivers are batch scripts
+    let suffix = if cfg!(target_os = "windows") { ".cmd" } else { "" };
+
+    toolchain_dir(ndk_home)
+        .join("bin")
+        .join(format!("{clang_target}{api_level}-clang{suffix}"))
+}
+
+/// Returns the target triple the NDK uses for the given Rust target
+pub(crate) fn clang_target(rust_target: &str) -> &str {
+    // The NDK names the 32-bit ARM compiler after the armv7a architecture
+    match rust_target {
+        "armv7-linux-androideabi" => "armv7a-linux-androideabi",
+        target => target,
+    }
+}
+
+/// Returns the archiver of the NDK
+pub(crate) fn ar_path(ndk_home: &Path) -> PathBuf {
+    let suffix = if cfg!(target_os = "windows") { ".exe" } else { "" };
+
+    toolchain_dir(ndk_home)
+        .join("bin")
+        .join(format!("llvm-ar{suffix}"))
+}
+
+/// Returns the object copy tool of the NDK, used to strip libraries
+pub(crate) fn objcopy_path(ndk_home: &Path) -> PathBuf {
+    let suffix = if cfg!(target_os = "windows") { ".exe" } else { "" };
+
+    toolchain_dir(ndk_home)
+        .join("bin")
+        .join(format!("llvm-objcopy{suffix}"))
+}
+
+/// Reads the NDK version (`Pkg.Revision`) from `source.properties` in the NDK root
+pub(crate) fn ndk_version(ndk_home: &Path) -> Option<String> {
+    let properties = fs::read_to_string(ndk_home.join("source.properties")).ok()?;
+
+    properties.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "Pkg.Revision").then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a fake NDK with sysroot directories for the given Rust target and API levels
+    fn fake_ndk(path: &Path, version: &str, rust_target: &str, api_levels: &[u32]) {
+        fs::create_dir_all(path).unwrap();
+        fs::write(
+            path.join("source.properties"),
+            format!("Pkg.Desc = Android NDK\nPkg.Revision = {version}\n"),
+        )
+        .unwrap();
+
+        let ndk = Ndk::at(path).unwrap();
+        for level in api_levels {
+            fs::create_dir_all(ndk.sysroot_lib_dir(rust_target).join(level.to_string())).unwrap();
+        }
+    }
+
+    fn sdk_search(sdk: &Path) -> NdkSearch {
+        NdkSearch {
+            ndk_dirs: vec![],
+            sdk_dirs: vec![sdk.to_path_buf()],
+        }
+    }
+
+    #[test]
+    fn newest_ndk_wins() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk/25.2.9519653"), "25.2.9519653", "aarch64-linux-android", &[21]);
+        fake_ndk(&sdk.path().join("ndk/27.0.12077973"), "27.0.12077973", "aarch64-linux-android", &[21]);
+        fake_ndk(&sdk.path().join("ndk/26.1.10909125"), "26.1.10909125", "aarch64-linux-android", &[21]);
+
+        let ndk = sdk_search(sdk.path()).find(None).unwrap();
+        assert_eq!(ndk.version.to_string(), "27.0.12077973");
+        assert_eq!(ndk.path, sdk.path().join("ndk/27.0.12077973"));
+    }
+
+    #[test]
+    fn requested_version_is_pinned() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk/26.1.10909125"), "26.1.10909125", "aarch64-linux-android", &[21]);
+        fake_ndk(&sdk.path().join("ndk/26.3.11579264"), "26.3.11579264", "aarch64-linux-android", &[21]);
+        fake_ndk(&sdk.path().join("ndk/27.0.12077973"), "27.0.12077973", "aarch64-linux-android", &[21]);
+        let search = sdk_search(sdk.path());
+
+        assert_eq!(search.find(Some("26")).unwrap().version.to_string(), "26.3.11579264");
+        assert_eq!(search.find(Some("26.1")).unwrap().version.to_string(), "26.1.10909125");
+        assert!(search.find(Some("28")).is_err());
+        assert!(search.find(Some("latest")).is_err());
+    }
+
+    #[test]
+    fn ndk_bundle_is_detected() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk-bundle"), "21.4.7075529", "aarch64-linux-android", &[21]);
+
+        let ndk = sdk_search(sdk.path()).find(None).unwrap();
+        assert_eq!(ndk.version.to_string(), "21.4.7075529");
+        assert_eq!(ndk.path, sdk.path().join("ndk-bundle"));
+    }
+
+    #[test]
+    fn explicit_ndk_is_preferred() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk/27.0.12077973"), "27.0.12077973", "aarch64-linux-android", &[21]);
+        let explicit = tempfile::tempdir().unwrap();
+        fake_ndk(explicit.path(), "25.2.9519653", "aarch64-linux-android", &[21]);
+
+        let search = NdkSearch {
+            ndk_dirs: vec![("ANDROID_NDK_HOME", explicit.path().to_path_buf())],
+            sdk_dirs: vec![sdk.path().to_path_buf()],
+        };
+        assert_eq!(search.find(None).unwrap().version.to_string(), "25.2.9519653");
+    }
+
+    #[test]
+    fn invalid_explicit_ndk_is_an_error() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk/27.0.12077973"), "27.0.12077973", "aarch64-linux-android", &[21]);
+
+        let missing = NdkSearch {
+            ndk_dirs: vec![("ANDROID_NDK_HOME", sdk.path().join("does-not-exist"))],
+            sdk_dirs: vec![sdk.path().to_path_buf()],
+        };
+        let error = missing.find(None).unwrap_err().to_string();
+        assert!(error.contains("ANDROID_NDK_HOME"), "{error}");
+
+        let not_an_ndk = NdkSearch {
+            ndk_dirs: vec![("ANDROID_NDK_ROOT", sdk.path().to_path_buf())],
+            sdk_dirs: vec![sdk.path().to_path_buf()],
+        };
+        let error = not_an_ndk.find(None).unwrap_err().to_string();
+        assert!(error.contains("ANDROID_NDK_ROOT"), "{error}");
+    }
+
+    #[test]
+    fn unsupported_api_levels_are_rejected() {
+        let sdk = tempfile::tempdir().unwrap();
+        fake_ndk(&sdk.path().join("ndk/26.1.10909125"), "26.1.10909125", "armv7-linux-androideabi", &[21, 24, 34]);
+        let ndk = sdk_search(sdk.path()).find(None).unwrap();
+
+        // The 32-bit ARM sysroot uses the unversioned triple
+        assert!(ndk.sysroot_lib_dir("armv7-linux-androideabi").ends_with("arm-linux-androideabi"));
+        assert_eq!(ndk.api_levels("armv7-linux-androideabi"), vec![21, 24, 34]);
+        assert!(ndk.validate_api_level("armv7-linux-androideabi", 24).is_ok());
+
+        let error = ndk.validate_api_level("armv7-linux-androideabi", 19).unwrap_err().to_string();
+        assert!(error.contains("Supported API levels: 21 to 34"), "{error}");
+        assert!(ndk.validate_api_level("x86_64-linux-android", 21).is_err());
+    }
+
+    #[test]
+    fn pre_release_versions_are_parsed() {
+        assert_eq!(NdkVersion::parse("27.0.11718014-beta1").unwrap().to_string(), "27.0.11718014");
+        assert!(NdkVersion::parse("").is_none());
+        assert!(NdkVersion::parse("r26").is_none());
+    }
+}
@@ -1,42 +1,70 @@
 use anyhow::{anyhow, Context, Result};
-use cargo_metadata::Package;
-// use convert_case::{Case, Casing};
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::{Metadata, Package};
 use dialoguer::{Input, MultiSelect};
+use itertools::Itertools;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use uniffi_bindgen::interface::ComponentInterface;
 
-use crate::android::android_target::{AndroidArch, AndroidTarget};
+use crate::android::android_target::{AndroidArch, AndroidOutput, AndroidTarget};
+use crate::android::elf::{exported_symbols, misaligned_load_segments, PAGE_SIZE_16K};
+use crate::android::ndk::{objcopy_path, Ndk, NdkSearch};
+use crate::android::proguard::consumer_rules;
 use crate::common::{
-    metadata::{metadata, MetadataExt},
-    models::{Config, FeatureOptions, LibType, Mode},
+    metadata::{library_target, Workspace},
+    models::{default_output_name, Config, CrateSelection, FeatureArgs, FeatureOptions, LibType, Mode},
+    path::recreate_dir,
+    project::ProjectConfig,
+    toolchain::ensure_targets_installed,
+};
+use crate::console::{
+    messages::*,
+    step::{run_step, run_step_with_commands},
+    theme::prompt_theme,
 };
-use crate::console::{messages::*, step::run_step_with_commands, theme::prompt_theme};
 use crate::ffi::kotlin::generate_kotlin_bindings_with_output;
+use crate::jvm::jvm_target::JvmTarget;
+use crate::jvm::package::build_jvm_libraries_with_output;
 use crate::android::aar::create_aar_with_output;
+use crate::android::gradle::{create_gradle_module_with_output, create_kmp_module_with_output};
+use crate::android::manifest::ManifestSettings;
+use crate::android::maven::{publish_aar_with_output, MavenCoordinates};
 
 #[allow(clippy::too_many_arguments)]
 pub fn build_android_package(
+    workspace: &Workspace,
+    selection: &CrateSelection,
     architectures: Option<Vec<AndroidArch>>,
     build_target: Option<&str>,
-    api_level: u32,
+    api_level: Option<u32>,
+    ndk_version: Option<String>,
     package_name: Option<String>,
-    aar_name: String,
+    aar_name: Option<String>,
+    output: Option<AndroidOutput>,
+    maven_repo: Option<Utf8PathBuf>,
     config: Config,
-    mode: Mode,
-    lib_type: LibType,
-    features: FeatureOptions,
+    mode: Option<Mode>,
+    lib_type: Option<LibType>,
+    features: FeatureArgs,
 ) -> Result<()> {
-    // Get the current crate
-    let crates = [metadata()
-        .current_crate()
-        .context("Current directory is not part of a crate!")?];
+    let crates = workspace.selected_crates(selection)?;
 
     if crates.len() == 1 {
         return build_android_package_for_crate(
+            workspace,
             crates[0],
             architectures.clone(),
             build_target,
             api_level,
+            ndk_version,
             package_name,
             aar_name,
+            output,
+            maven_repo,
+            false,
             &config,
             mode,
             lib_type,
@@ -46,6 +74,10 @@ pub fn build_android_package(
         return Err(anyhow!(
             "Package name can only be specified when building a single crate!"
         ));
+    } else if aar_name.is_some() {
+        return Err(anyhow!(
+            "AAR name can only be specified when building a single crate!"
+        ));
     }
 
     crates
@@ -53,67 +85,148 @@ pub fn build_android_package(
         .map(|current_crate| {
             info!(&config, "Packaging crate {}", current_crate.name);
             build_android_package_for_crate(
+                workspace,
                 current_crate,
                 architectures.clone(),
                 build_target,
                 api_level,
+                ndk_version.clone(),
                 None,
-                aar_name.clone(),
+                None,
+                output,
+                maven_repo.clone(),
+                true,
                 &config,
                 mode,
-                lib_type.clone(),
+                lib_type,
                 features.clone(),
             )
         })
         .filter_map(|result| result.err())
         .collect::<Vec<_>>()
         .into_iter()
-        .fold(Ok(()), |acc, err| {
-            if let Err(e) = acc {
-                Err(e.context(err))
-            } else {
-                Err(err)
-            }
-        })
+        .reduce(|acc, err| acc.context(err))
+        .map_or(Ok(()), Err)
 }
 
 #[allow(clippy::too_many_arguments)]
 fn build_android_package_for_crate(
+    workspace: &Workspace,
     current_crate: &Package,
     architectures: Option<Vec<AndroidArch>>,
     build_target: Option<&str>,
-    api_level: u32,
+    api_level: Option<u32>,
+    ndk_version: Option<String>,
     package_name: Option<String>,
-    aar_name: String,
+    aar_name: Option<String>,
+    output: Option<AndroidOutput>,
+    maven_repo: Option<Utf8PathBuf>,
+    multiple_crates: bool,
     config: &Config,
-    mode: Mode,
-    lib_type: LibType,
-    features: FeatureOptions,
+    mode: Option<Mode>,
+    lib_type: Option<LibType>,
+    features: FeatureArgs,
 ) -> Result<()> {
-    // Verify that ANDROID_NDK_HOME is properly set
-    if let Err(e) = std::env::var("ANDROID_NDK_HOME") {
-        return Err(anyhow!(
-            "ANDROID_NDK_HOME environment variable is not set. Please install Android NDK and set this variable: {}",
-            e
-        ));
-    }
-    let lib = current_crate
-        .targets
-        .iter()
-        .find(|t| t.kind.contains(&"lib".to_owned()))
-        .context("No library tag defined in Cargo.toml!")?;
+    let lib = library_target(current_crate)?;
+
+    let project = ProjectConfig::load(current_crate, config)?;
+
+    let mode = project.mode(mode);
+    let features = project.features(features);
+    let lib_type = lib_type.or(project.android.lib_type).unwrap_or(LibType::Dynamic);
+    let api_level = api_level.or(project.android.api_level).unwrap_or(21);
+    let output = output.or(project.android.output).unwrap_or(AndroidOutput::Aar);
+    let manifest = ManifestSettings::new(
+        api_level,
+        project.android.permissions.as_deref().unwrap_or_default(),
+        project.android.extract_native_libs,
+    )?;
+    let aar_name = aar_name
+        .or(project.android.aar_name)
+        .unwrap_or_else(|| default_output_name("RustLibrary", current_crate, multiple_crates));
+
+    let crate_dir = current_crate
+        .manifest_path
+        .parent()
+        .expect("The Cargo.toml path should end with /Cargo.toml");
 
     let crate_name = current_crate.name.to_lowercase();
     let default_package_name = format!("com.{}.{}", crate_name, crate_name);
-    let package_name = package_name.unwrap_or_else(|| 
-        prompt_package_name(&default_package_name, config.accept_all));
+    let package_name = package_name
+        .or(project.android.package_name)
+        .unwrap_or_else(|| prompt_package_name(&default_package_name, config.accept_all));
+
+    let maven_repo = match maven_repo {
+        Some(repo) => Some(repo),
+        None => project
+            .android
+            .maven_repo
+            .map(|repo| {
+                Utf8PathBuf::try_from(crate_dir.as_std_path().join(repo))
+                    .context("The Maven repository path in the configuration is not valid UTF-8")
+            })
+            .transpose()?,
+    };
+    let maven = match maven_repo {
+        Some(_) if output != AndroidOutput::Aar => {
+            return Err(anyhow!(
+                "Publishing to a Maven repository is only supported for the aar output!"
+            ));
+        }
+        Some(repo) => {
+            let coordinates = MavenCoordinates {
+                group_id: project
+                    .android
+                    .maven_group_id
+                    .unwrap_or_else(|| package_name.clone()),
+                artifact_id: project
+                    .android
+                    .maven_artifact_id
+                    .unwrap_or_else(|| current_crate.name.to_string()),
+                version: project
+                    .android
+                    .maven_version
+                    .unwrap_or_else(|| current_crate.version.to_string()),
+            };
+            coordinates.validate()?;
+            Some((repo, coordinates))
+        }
+        None => None,
+    };
 
-    let architectures = architectures.unwrap_or_else(|| prompt_architectures(config.accept_all));
+    let architectures = architectures
+        .or(project.android.archs)
+        .unwrap_or_else(|| prompt_architectures(config.accept_all));
 
     if architectures.is_empty() {
         return Err(anyhow!("At least 1 architecture needs to be selected!"));
     }
 
+    let ndk = NdkSearch::from_env().find(ndk_version.or(project.android.ndk_version).as_deref())?;
+    info!(config, "Using Android NDK {} at {}", ndk.version, ndk.path.display());
+
+    // The JVM can only load dynamic libraries
+    let jvm_targets = match output {
+        AndroidOutput::KmpModule if lib_type == LibType::Static => {
+            return Err(anyhow!(
+                "Kotlin Multiplatform modules can only be built with dynamic libraries!"
+            ));
+        }
+        AndroidOutput::KmpModule => {
+            let jvm_targets = project
+                .jvm
+                .targets
+                .unwrap_or_else(|| JvmTarget::host().into_iter().collect());
+            if jvm_targets.is_empty() {
+                return Err(anyhow!(
+                    "No JVM target for this host! Configure targets in the [jvm] section of rusify.toml."
+                ));
+            }
+            jvm_targets
+        }
+        AndroidOutput::Aar | AndroidOutput::GradleModule => vec![],
+    };
+
     if lib_type == LibType::Static {
         warning!(
             &config,
@@ -127,32 +240,145 @@ fn build_android_package_for_crate(
         .collect();
 
     if let Some(build_target) = build_target {
-        targets.retain_mut(|android_target| match android_target {
-            AndroidTarget { architectures, .. } => {
-                architectures.iter().any(|arch| *arch == build_target)
-            }
-        });
+        targets.retain(|android_target| android_target.architectures().contains(&build_target));
         if targets.is_empty() {
             return Err(anyhow!("No matching build target for {}", build_target));
         }
     }
 
+    for target in &targets {
+        for arch in target.architectures() {
+            ndk.validate_api_level(arch, api_level)?;
+        }
+    }
+
+    let installable: Vec<_> = targets
+        .iter()
+        .flat_map(|t| t.architectures().iter().copied())
+        .collect();
+    ensure_targets_installed(&installable, config)?;
+
+    let target_dir = workspace.target_dir();
     let crate_name = lib.name.replace('-', "_");
     for target in &targets {
-        build_with_output(target, &crate_name, mode, lib_type, config, &features)?;
+        build_with_output(
+            target,
+            &current_crate.manifest_path,
+            &ndk.path,
+            &target_dir,
+            mode,
+            config,
+            &features,
+        )?;
     }
 
-    generate_kotlin_bindings_with_output(&targets, &crate_name, mode, lib_type, config, &package_name)?;
+    if !jvm_targets.is_empty() {
+        build_jvm_libraries_with_output(
+            &jvm_targets,
+            &current_crate.manifest_path,
+            &target_dir,
+            &crate_name,
+            mode,
+            &features,
+            config,
+        )?;
+    }
 
-    create_aar_with_output(
-        &targets,
-        &crate_name,
-        &package_name,
-        &aar_name,
-        mode,
-        lib_type,
-        config,
-    )?;
+    generate_kotlin_bindings_with_output(workspace, &targets, &crate_name, mode, lib_type, config, &package_name)?;
+
+    let lib_path = targets[0].library_path(&target_dir, &crate_name, mode, lib_type);
+    let components = load_components(&workspace.metadata, Utf8Path::new(&lib_path))?;
+
+    let user_rules = project
+        .android
+        .consumer_proguard_rules
+        .map(|file| {
+            let path = crate_dir.as_std_path().join(file);
+            fs::read_to_string(&path)
+                .with_context(|| format!("Could not read the ProGuard rules in {}", path.display()))
+        })
+        .transpose()?;
+    let proguard_rules = consumer_rules(&components, &package_name, user_rules.as_deref())?;
+
+    if lib_type == LibType::Dynamic {
+        post_process_with_output(
+            &components,
+            &targets,
+            &ndk,
+            &target_dir,
+            &crate_name,
+            &aar_name,
+            mode,
+            config,
+        )?;
+    }
+
+    match output {
+        AndroidOutput::Aar => {
+            let classpath: Vec<_> = project
+                .android
+                .kotlin_classpath
+                .iter()
+                .flatten()
+                .map(|jar| crate_dir.as_std_path().join(jar))
+                .collect();
+
+            create_aar_with_output(
+                &targets,
+                &ndk,
+                &target_dir,
+                &crate_name,
+                &package_name,
+                &aar_name,
+                &classpath,
+                &manifest,
+                &proguard_rules,
+                mode,
+                lib_type,
+                config,
+            )?;
+
+            if let Some((repo, coordinates)) = &maven {
+                publish_aar_with_output(
+                    repo,
+                    coordinates,
+                    &current_crate.name,
+                    current_crate.description.as_deref(),
+                    config.output_path(&format!("{aar_name}.aar")).as_std_path(),
+                    config
+                        .output_path(&format!("{aar_name}-sources.jar"))
+                        .as_std_path(),
+                    config,
+                )?;
+            }
+        }
+        AndroidOutput::GradleModule => create_gradle_module_with_output(
+            &targets,
+            &ndk,
+            &target_dir,
+            &crate_name,
+            &package_name,
+            &aar_name,
+            &manifest,
+            &proguard_rules,
+            mode,
+            lib_type,
+            config,
+        )?,
+        AndroidOutput::KmpModule => create_kmp_module_with_output(
+            &targets,
+            &jvm_targets,
+            &ndk,
+            &target_dir,
+            &crate_name,
+            &package_name,
+            &aar_name,
+            &manifest,
+            &proguard_rules,
+            mode,
+            config,
+        )?,
+    }
 
     Ok(())
 }
@@ -191,18 +417,19 @@ fn prompt_package_name(default: &str, accept_all: bool) -> String {
 
 fn build_with_output(
     target: &AndroidTarget,
-    _lib_name: &str,
+    manifest_path: &Utf8Path,
+    ndk_home: &Path,
+    target_dir: &Utf8Path,
     mode: Mode,
-    _lib_type: LibType,
     config: &Config,
     features: &FeatureOptions,
 ) -> Result<()> {
     // First set up the environment with Rust file operations
-    target.setup_environment()
+    target.setup_environment(ndk_home, target_dir)
         .context(format!("Failed to set up build environment for {}", target.display_name()))?;
     
     // Then run cargo build commands
-    let mut commands = target.cargo_build_commands(mode, features);
+    let mut commands = target.cargo_build_commands(manifest_path, ndk_home, target_dir, mode, features);
     for command in &mut commands {
         command.env("CARGO_TERM_COLOR", "always");
     }
@@ -214,4 +441,214 @@ fn build_with_output(
     )?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Checks the built dynamic libraries and prepares them for packaging
+///
+/// In release mode, the debug info is moved to `<aar name>-symbols/<abi>/lib<name>.so.dbg` in the
+/// output directory, which can be uploaded to Google Play for crash symbolication.
+#[allow(clippy::too_many_arguments)]
+fn post_process_with_output(
+    components: &[ComponentInterface],
+    targets: &[AndroidTarget],
+    ndk: &Ndk,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    aar_name: &str,
+    mode: Mode,
+    config: &Config,
+) -> Result<()> {
+    let report = run_step(config, "Checking native libraries...", || {
+        check_libraries(components, targets, target_dir, lib_name, mode)
+    })?;
+    if let Some(report) = report {
+        // Debug builds are only used locally, so they can still be installed on most devices
+        match mode {
+            Mode::Debug => warning!(config, "The native libraries have problems:{report}"),
+            Mode::Release => return Err(anyhow!("The native libraries cannot be packaged:{report}")),
+        }
+    }
+
+    let symbols_dir = config.output_path(&format!("{aar_name}-symbols"));
+    if let Mode::Release = mode {
+        recreate_dir(&symbols_dir)?;
+    }
+
+    let objcopy = objcopy_path(&ndk.path);
+    let mut commands = vec![];
+    for target in targets {
+        let lib_path = target.library_path(target_dir, lib_name, mode, LibType::Dynamic);
+        let packaged_path = target.packaged_library_path(target_dir, lib_name, mode);
+        if let Some(parent) = packaged_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match mode {
+            Mode::Debug => {
+                fs::copy(&lib_path, &packaged_path)
+                    .context(format!("Failed to copy library for {}", target.display_name()))?;
+            }
+            Mode::Release => {
+                let abi_dir = symbols_dir.join(target.ndk_arch);
+                fs::create_dir_all(&abi_dir)?;
+                let debug_file = abi_dir.join(format!("lib{lib_name}.so.dbg"));
+
+                let mut keep_debug = Command::new(&objcopy);
+                keep_debug.arg("--only-keep-debug").arg(&lib_path).arg(&debug_file);
+
+                let mut strip = Command::new(&objcopy);
+                strip
+                    .arg("--strip-all")
+                    .arg(format!("--add-gnu-debuglink={debug_file}"))
+                    .arg(&lib_path)
+                    .arg(&packaged_path);
+
+                commands.extend([keep_debug, strip]);
+            }
+        }
+    }
+
+    if !commands.is_empty() {
+        run_step_with_commands(config, "Stripping debug info...", &mut commands)?;
+        info!(config, "Debug symbols for crash symbolication written to {symbols_dir}");
+    }
+
+    Ok(())
+}
+
+/// Verifies that the libraries support 16 KB page sizes and export all UniFFI symbols
+///
+/// All problems of all targets are collected into a single report, which is `None` if there are
+/// none.
+fn check_libraries(
+    components: &[ComponentInterface],
+    targets: &[AndroidTarget],
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    mode: Mode,
+) -> Result<Option<String>> {
+    let expected = expected_ffi_symbols(components);
+    let mut report = String::new();
+    let (mut misaligned, mut missing_symbols) = (false, false);
+
+    for target in targets {
+        let lib_path = target.library_path(target_dir, lib_name, mode, LibType::Dynamic);
+        let mut problems = vec![];
+        for (index, align) in misaligned_load_segments(Path::new(&lib_path))? {
+            misaligned = true;
+            problems.push(format!(
+                "LOAD segment {index} is aligned to {align} bytes, devices with 16 KB pages require at least {PAGE_SIZE_16K}"
+            ));
+        }
+
+        let exported = exported_symbols(Path::new(&lib_path))?;
+        let missing: Vec<_> = expected.difference(&exported).collect();
+        if !exported.iter().any(|symbol| is_ffi_symbol(symbol)) {
+            missing_symbols = true;
+            problems.push("No uniffi_* or ffi_* symbols are exported".to_string());
+        } else if !missing.is_empty() {
+            missing_symbols = true;
+            let shown = missing.iter().take(10).join(", ");
+            let more = match missing.len() {
+                len if len > 10 => format!(" and {} more", len - 10),
+                _ => String::new(),
+            };
+            problems.push(format!(
+                "{} expected symbol(s) are not exported: {shown}{more}",
+                missing.len()
+            ));
+        }
+
+        if !problems.is_empty() {
+            report.push_str(&format!("\n  {} ({lib_path}):", target.display_name()));
+            for problem in problems {
+                report.push_str(&format!("\n    - {problem}"));
+            }
+        }
+    }
+
+    if report.is_empty() {
+        return Ok(None);
+    }
+
+    if misaligned {
+        report.push_str("\nLink with `-C link-arg=-Wl,-z,max-page-size=16384` or use Android NDK r27 or newer to support devices with 16 KB pages.");
+    }
+    if missing_symbols {
+        report.push_str("\nMake sure the crate type includes cdylib and that no linker arguments hide the UniFFI symbols.");
+    }
+
+    Ok(Some(report))
+}
+
+/// Returns the `uniffi_*` and `ffi_*` functions the UniFFI components define
+///
+/// All targets are built from the same crate, so they have to export the same symbols.
+fn expected_ffi_symbols(components: &[ComponentInterface]) -> BTreeSet<String> {
+    components
+        .iter()
+        .flat_map(|ci| ci.iter_ffi_function_definitions())
+        .map(|function| function.name().to_string())
+        .filter(|name| is_ffi_symbol(name))
+        .collect()
+}
+
+/// Reads the UniFFI component interfaces embedded in the built library
+fn load_components(metadata: &Metadata, lib_path: &Utf8Path) -> Result<Vec<ComponentInterface>> {
+    let components = uniffi_bindgen::library_mode::find_components(
+        lib_path,
+        &uniffi_bindgen::cargo_metadata::CrateConfigSupplier::from(metadata.clone()),
+    )
+    .context(format!("Could not read the UniFFI components from {lib_path}"))?;
+
+    Ok(components.into_iter().map(|component| component.ci).collect())
+}
+
+fn is_ffi_symbol(name: &str) -> bool {
+    name.starts_with("uniffi_") || name.starts_with("ffi_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::android::elf::tests::shared_library;
+
+    /// Writes a library built for ARM64 to where cargo would put it and returns the target
+    fn built_library(target_dir: &Utf8Path, bytes: &[u8]) -> AndroidTarget {
+        let target = AndroidArch::ARM64V8A.target(21);
+        let lib_path = target.library_path(target_dir, "demo", Mode::Debug, LibType::Dynamic);
+        fs::create_dir_all(Utf8Path::new(&lib_path).parent().unwrap()).unwrap();
+        fs::write(&lib_path, bytes).unwrap();
+        target
+    }
+
+    #[test]
+    fn valid_libraries_pass_the_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let target = built_library(target_dir, &shared_library(&[0x4000], &[], &["uniffi_demo_fn_add"]));
+
+        let report = check_libraries(&[], &[target], target_dir, "demo", Mode::Debug).unwrap();
+        assert_eq!(report, None);
+    }
+
+    #[test]
+    fn problems_are_collected_into_a_readable_report() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = Utf8Path::from_path(dir.path()).unwrap();
+        let target = built_library(target_dir, &shared_library(&[0x1000], &[], &["demo_add"]));
+
+        let report = check_libraries(&[], &[target], target_dir, "demo", Mode::Debug)
+            .unwrap()
+            .unwrap();
+        assert!(report.contains("ARM64-v8a ("), "{report}");
+        assert!(
+            report.contains("- LOAD segment 0 is aligned to 4096 bytes, devices with 16 KB pages require at least 16384"),
+            "{report}"
+        );
+        assert!(report.contains("- No uniffi_* or ffi_* symbols are exported"), "{report}");
+        assert!(report.contains("-Wl,-z,max-page-size=16384"), "{report}");
+        assert!(report.contains("crate type includes cdylib"), "{report}");
+    }
+}
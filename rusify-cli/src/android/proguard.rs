@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use askama::Template;
+use heck::ToUpperCamelCase;
+use itertools::Itertools;
+use uniffi_bindgen::interface::{ComponentInterface, FfiDefinition};
+
+use crate::common::templating::{ProguardCallbackInterface, ProguardRules};
+
+/// Structures defined by the UniFFI runtime of every Kotlin binding
+const RUNTIME_STRUCTURES: [&str; 3] = ["RustBuffer", "ForeignBytes", "UniffiRustCallStatus"];
+
+/// Renders the consumer ProGuard/R8 rules keeping everything JNA accesses through reflection
+///
+/// The structures, function pointers and callback interfaces are collected from the component
+/// interfaces, whose Kotlin bindings are all generated into `package_name`. `user_rules` are
+/// appended as is.
+pub(crate) fn consumer_rules(
+    components: &[ComponentInterface],
+    package_name: &str,
+    user_rules: Option<&str>,
+) -> Result<String> {
+    let mut structures: Vec<_> = RUNTIME_STRUCTURES.map(String::from).to_vec();
+    let mut callbacks = vec![];
+
+    for definition in components.iter().flat_map(|ci| ci.ffi_definitions()) {
+        match definition {
+            FfiDefinition::Struct(structure) => structures.push(ffi_name(structure.name())),
+            FfiDefinition::CallbackFunction(callback) => callbacks.push(ffi_name(callback.name())),
+            FfiDefinition::Function(_) => {}
+        }
+    }
+
+    // Trait interfaces with foreign implementations get the same vtable as callback interfaces
+    let callback_interfaces: Vec<_> = components
+        .iter()
+        .flat_map(|ci| {
+            let callback_interfaces = ci.callback_interface_definitions().iter().map(|cbi| cbi.name());
+            let trait_interfaces = ci
+                .object_definitions()
+                .iter()
+                .filter(|obj| obj.has_callback_interface())
+                .map(|obj| obj.name());
+            callback_interfaces.chain(trait_interfaces)
+        })
+        .map(|name| ProguardCallbackInterface {
+            name: name.to_upper_camel_case(),
+            vtable_impl: format!("uniffiCallbackInterface{name}"),
+        })
+        .collect();
+
+    ProguardRules {
+        package_name,
+        structures: &structures.into_iter().unique().collect_vec(),
+        callbacks: &callbacks.into_iter().unique().collect_vec(),
+        callback_interfaces: &callback_interfaces,
+        user_rules: user_rules.map(str::trim_end),
+    }
+    .render()
+    .context("Failed to render the ProGuard rules template")
+}
+
+/// Returns the Kotlin name of an FFI structure or callback, mirroring the Kotlin binding generator
+fn ffi_name(name: &str) -> String {
+    format!("Uniffi{}", name.to_upper_camel_case())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UDL: &str = r#"
+        namespace demo {
+            u32 add(u32 a, u32 b);
+        };
+
+        callback interface Logger {
+            void log(string message);
+        };
+    "#;
+
+    fn rules(user_rules: Option<&str>) -> String {
+        let ci = ComponentInterface::from_webidl(UDL, "demo").unwrap();
+        consumer_rules(&[ci], "com.example.demo", user_rules).unwrap()
+    }
+
+    #[test]
+    fn jna_and_the_bindings_are_kept() {
+        let rules = rules(None);
+
+        assert!(
+            rules.contains("-keep class com.sun.jna.** { *; }"),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep class * implements com.sun.jna.** { *; }"),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep interface com.example.demo.UniffiLib { *; }"),
+            "{rules}"
+        );
+        for structure in RUNTIME_STRUCTURES {
+            assert!(
+                rules.contains(&format!(
+                    "-keep class com.example.demo.{structure} {{ *; }}"
+                )),
+                "{rules}"
+            );
+        }
+        assert!(
+            !rules.contains("Rules from the rusify configuration"),
+            "{rules}"
+        );
+    }
+
+    #[test]
+    fn callback_interfaces_are_kept() {
+        let rules = rules(None);
+
+        // The vtable is a JNA Structure subclass filled in by Kotlin and read by Rust
+        assert!(
+            rules.contains(
+                "-keep class com.example.demo.UniffiVTableCallbackInterfaceLogger { *; }"
+            ),
+            "{rules}"
+        );
+        assert!(
+            rules.contains(
+                "-keep interface com.example.demo.UniffiCallbackInterfaceLoggerMethod0 { *; }"
+            ),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep interface com.example.demo.UniffiCallbackInterfaceFree { *; }"),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep interface com.example.demo.Logger { *; }"),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep class com.example.demo.uniffiCallbackInterfaceLogger { *; }"),
+            "{rules}"
+        );
+        assert!(
+            rules.contains("-keep class com.example.demo.uniffiCallbackInterfaceLogger$* { *; }"),
+            "{rules}"
+        );
+    }
+
+    #[test]
+    fn user_rules_are_appended() {
+        let rules = rules(Some("-keep class com.example.Extra\n\n"));
+
+        assert!(
+            rules.ends_with("# Rules from the rusify configuration\n-keep class com.example.Extra"),
+            "{rules}"
+        );
+    }
+}
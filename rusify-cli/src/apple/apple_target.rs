@@ -1,6 +1,9 @@
-use crate::common::metadata::{metadata, MetadataExt};
+use crate::apple::macho::create_universal_binary;
 use crate::common::models::{FeatureOptions, LibType, Mode};
+use crate::Result;
+use camino::Utf8Path;
 use execute::command;
+use std::path::Path;
 use std::process::Command;
 
 #[derive(Debug, Clone)]
@@ -12,7 +15,7 @@ pub struct AppleTarget {
 }
 
 const PLATFORM_COUNT: usize = 5;
-#[derive(clap::ValueEnum, Clone, Copy, Debug)]
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ApplePlatform {
     IOS,
     MacOS,
@@ -28,19 +31,19 @@ pub struct ApplePlatformTarget {
 }
 
 impl ApplePlatform {
-    pub(crate) fn into_apple_platform_target(&self) -> Vec<ApplePlatformTarget> {
+    pub(crate) fn into_apple_platform_target(self) -> Vec<ApplePlatformTarget> {
         match self {
             ApplePlatform::MacOS => vec![ApplePlatformTarget {
-                platform: *self,
+                platform: self,
                 is_simulator: false,
             }],
             _ => vec![
                 ApplePlatformTarget {
-                    platform: *self,
+                    platform: self,
                     is_simulator: false,
                 },
                 ApplePlatformTarget {
-                    platform: *self,
+                    platform: self,
                     is_simulator: true,
                 },
             ],
@@ -49,7 +52,12 @@ impl ApplePlatform {
 }
 
 impl AppleTarget {
-    fn cargo_build_commands(&self, mode: Mode, features: &FeatureOptions) -> Vec<Command> {
+    fn cargo_build_commands(
+        &self,
+        manifest_path: &Utf8Path,
+        mode: Mode,
+        features: &FeatureOptions,
+    ) -> Vec<Command> {
         self.architectures
             .iter()
             .map(|arch| {
@@ -59,6 +67,7 @@ impl AppleTarget {
                 } else {
                     command("cargo build")
                 };
+                cmd.arg("--manifest-path").arg(manifest_path);
                 cmd.arg("--target").arg(arch);
 
                 match mode {
@@ -83,61 +92,66 @@ impl AppleTarget {
             .collect()
     }
 
-    fn lipo_commands(&self, lib_name: &str, mode: Mode, lib_type: LibType) -> Vec<Command> {
+    /// Merges the libraries of all architectures into the universal library of this target
+    ///
+    /// Does nothing for targets with a single architecture, whose library is used as is.
+    pub(crate) fn create_universal_library(
+        &self,
+        target_dir: &Utf8Path,
+        lib_name: &str,
+        mode: Mode,
+        lib_type: LibType,
+    ) -> Result<()> {
         if self.architectures.len() <= 1 {
-            return vec![];
+            return Ok(());
         }
 
-        let path = self.library_directory(mode);
-
-        let target = metadata().target_dir();
         let target_name = library_file_name(lib_name, lib_type);
         let component_paths: Vec<_> = self
             .architectures
             .iter()
-            .map(|arch| format!("{target}/{arch}/{mode}/{target_name}"))
+            .map(|arch| target_dir.join(arch).join(mode.to_string()).join(&target_name))
+            .collect();
+        let libraries: Vec<_> = self
+            .architectures
+            .iter()
+            .zip(&component_paths)
+            .map(|(arch, path)| (*arch, path.as_std_path()))
             .collect();
-        let args = component_paths.join(" ");
-        let target_path = self.library_path(lib_name, mode, lib_type);
+        let target_path = self.library_path(target_dir, lib_name, mode, lib_type);
 
-        let make_dir = command(format!("mkdir -p {path}"));
-        let lipo = command(format!("lipo {args} -create -output {target_path}"));
-        vec![make_dir, lipo]
+        create_universal_binary(&libraries, Path::new(&target_path))
     }
 
-    fn rpath_install_id_commands(
+    /// Generates the commands setting the install name of the dynamic library to `@rpath`
+    pub(crate) fn rpath_install_id_commands(
         &self,
+        target_dir: &Utf8Path,
         lib_name: &str,
         mode: Mode,
         lib_type: LibType,
     ) -> Vec<Command> {
         if matches!(lib_type, LibType::Dynamic) {
-            vec![command(format!(
-                "install_name_tool -id @rpath/{} {}",
-                library_file_name(lib_name, lib_type),
-                self.library_path(lib_name, mode, lib_type)
-            ))]
+            let mut cmd = Command::new("install_name_tool");
+            cmd.arg("-id")
+                .arg(format!("@rpath/{}", library_file_name(lib_name, lib_type)))
+                .arg(self.library_path(target_dir, lib_name, mode, lib_type));
+            vec![cmd]
         } else {
             vec![]
         }
     }
 
-    /// Generates all commands necessary to build this target
+    /// Generates the cargo commands building the library of every architecture of this target
     ///
-    /// This function returns a list of commands that should be executed in their given
-    /// order to build this target (and bundle architecture targets with lipo if it is a universal target).
+    /// Universal targets are assembled afterwards with [`AppleTarget::create_universal_library`].
     pub fn commands(
         &self,
-        lib_name: &str,
+        manifest_path: &Utf8Path,
         mode: Mode,
-        lib_type: LibType,
         features: &FeatureOptions,
     ) -> Vec<Command> {
-        self.cargo_build_commands(mode, features)
-            .into_iter()
-            .chain(self.lipo_commands(lib_name, mode, lib_type))
-            .chain(self.rpath_install_id_commands(lib_name, mode, lib_type))
-            .collect()
+        self.cargo_build_commands(manifest_path, mode, features)
     }
 
     /// Returns the names of all target architectures for this target
@@ -156,24 +170,67 @@ impl AppleTarget {
         self.platform
     }
 
-    pub fn library_directory(&self, mode: Mode) -> String {
+    /// Returns the architecture names Apple uses for this target, e.g. `arm64` for `aarch64-apple-ios`
+    ///
+    /// They are sorted like in the XCFrameworks created by xcodebuild.
+    pub(crate) fn apple_architectures(&self) -> Vec<&'static str> {
+        let mut architectures: Vec<_> = self
+            .architectures
+            .iter()
+            .map(|triple| match triple.split('-').next().unwrap_or(triple) {
+                "aarch64" => "arm64",
+                architecture => architecture,
+            })
+            .collect();
+        architectures.sort_unstable();
+        architectures.dedup();
+        architectures
+    }
+
+    /// Returns the `SupportedPlatformVariant` of this target in an XCFramework, if any
+    pub(crate) fn platform_variant(&self) -> Option<&'static str> {
+        if self.platform.is_simulator {
+            Some("simulator")
+        } else if self.architectures.iter().any(|triple| triple.ends_with("-macabi")) {
+            Some("maccatalyst")
+        } else {
+            None
+        }
+    }
+
+    /// Returns the identifier of this target's library in an XCFramework, e.g. `ios-arm64_x86_64-simulator`
+    pub(crate) fn library_identifier(&self) -> String {
+        let platform = self.platform.platform.xcframework_platform();
+        let architectures = self.apple_architectures().join("_");
+
+        match self.platform_variant() {
+            Some(variant) => format!("{platform}-{architectures}-{variant}"),
+            None => format!("{platform}-{architectures}"),
+        }
+    }
+
+    pub fn library_directory(&self, target_dir: &Utf8Path, mode: Mode) -> String {
         let mode = match mode {
             Mode::Debug => "debug",
             Mode::Release => "release",
         };
 
-        let target = metadata().target_dir();
-
         match self.universal_name {
-            Some(universal_name) => format!("{target}/{universal_name}/{mode}"),
-            None => format!("{target}/{}/{mode}", self.architectures[0]),
+            Some(universal_name) => format!("{target_dir}/{universal_name}/{mode}"),
+            None => format!("{target_dir}/{}/{mode}", self.architectures[0]),
         }
     }
 
-    pub fn library_path(&self, lib_name: &str, mode: Mode, lib_type: LibType) -> String {
+    pub fn library_path(
+        &self,
+        target_dir: &Utf8Path,
+        lib_name: &str,
+        mode: Mode,
+        lib_type: LibType,
+    ) -> String {
         format!(
             "{}/{}",
-            self.library_directory(mode),
+            self.library_directory(target_dir, mode),
             library_file_name(lib_name, lib_type)
         )
     }
@@ -229,10 +286,55 @@ impl ApplePlatform {
             ApplePlatform::VisionOS => true,
         }
     }
+
+    /// The name of the platform in the `platforms` of a Package.swift
+    pub(crate) fn swift_platform_name(&self) -> &'static str {
+        match self {
+            ApplePlatform::IOS => "iOS",
+            ApplePlatform::MacOS => "macOS",
+            ApplePlatform::TvOS => "tvOS",
+            ApplePlatform::WatchOS => "watchOS",
+            ApplePlatform::VisionOS => "visionOS",
+        }
+    }
+
+    /// The deployment target declared in the Package.swift if none is configured
+    pub(crate) fn default_deployment_target(&self) -> &'static str {
+        match self {
+            ApplePlatform::IOS => "13.0",
+            ApplePlatform::MacOS => "10.15",
+            ApplePlatform::TvOS => "13.0",
+            ApplePlatform::WatchOS => "6.0",
+            ApplePlatform::VisionOS => "1.0",
+        }
+    }
+
+    /// The first swift-tools-version whose PackageDescription knows the platform
+    pub(crate) fn min_swift_tools_version(&self) -> &'static str {
+        match self {
+            ApplePlatform::VisionOS => "5.9",
+            _ => "5.5",
+        }
+    }
+
+    /// The `SupportedPlatform` of the platform's libraries in an XCFramework
+    pub(crate) fn xcframework_platform(&self) -> &'static str {
+        match self {
+            ApplePlatform::IOS => "ios",
+            ApplePlatform::MacOS => "macos",
+            ApplePlatform::TvOS => "tvos",
+            ApplePlatform::WatchOS => "watchos",
+            ApplePlatform::VisionOS => "xros",
+        }
+    }
 }
 
 impl ApplePlatformTarget {
-    pub(crate) fn target(&self) -> AppleTarget {
+    /// Returns the target built for this platform
+    ///
+    /// macOS and the iOS Simulator are universal targets including Intel Macs unless
+    /// `apple_silicon_only` is set.
+    pub(crate) fn target(&self, apple_silicon_only: bool) -> AppleTarget {
         use ApplePlatform::*;
         match (self.platform, self.is_simulator) {
             (IOS, false) => AppleTarget {
@@ -241,18 +343,30 @@ impl ApplePlatformTarget {
                 display_name: "iOS",
                 platform: *self,
             },
-            (IOS, true) => AppleTarget {
+            (IOS, true) if apple_silicon_only => AppleTarget {
                 universal_name: None,
                 architectures: vec!["aarch64-apple-ios-sim"],
                 display_name: "iOS Simulator",
                 platform: *self,
             },
-            (MacOS, _) => AppleTarget {
+            (IOS, true) => AppleTarget {
+                universal_name: Some("universal-ios-sim"),
+                architectures: vec!["aarch64-apple-ios-sim", "x86_64-apple-ios"],
+                display_name: "iOS Simulator",
+                platform: *self,
+            },
+            (MacOS, _) if apple_silicon_only => AppleTarget {
                 universal_name: None,
                 architectures: vec!["aarch64-apple-darwin"],
                 display_name: "macOS",
                 platform: *self,
             },
+            (MacOS, _) => AppleTarget {
+                universal_name: Some("universal-macos"),
+                architectures: vec!["aarch64-apple-darwin", "x86_64-apple-darwin"],
+                display_name: "macOS",
+                platform: *self,
+            },
             (TvOS, false) => AppleTarget {
                 universal_name: None,
                 architectures: vec!["aarch64-apple-tvos"],
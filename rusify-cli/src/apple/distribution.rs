@@ -0,0 +1,249 @@
+use anyhow::{anyhow, Context, Result};
+use camino::Utf8Path;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{Seek, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{DateTime, ZipWriter};
+
+use crate::common::models::Config;
+use crate::console::{messages::info, step::run_step};
+use crate::templating::RemoteBinaryTarget;
+
+/// How the XCFramework is referenced by the Swift package
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    /// The XCFramework is part of the package and referenced by its path
+    Local,
+    /// The XCFramework is zipped to be hosted on a server and referenced by its URL and checksum
+    Remote,
+}
+
+/// Checks that SwiftPM accepts binary targets below `url_base` and returns it without trailing slashes
+pub(crate) fn validate_url_base(url_base: &str) -> Result<String> {
+    let url_base = url_base.trim_end_matches('/');
+    let host = url_base.strip_prefix("https://").unwrap_or_default();
+
+    if host.is_empty() || url_base.chars().any(char::is_whitespace) {
+        return Err(anyhow!(
+            "Invalid URL base \"{url_base}\"! Swift packages only download binary targets from https:// URLs."
+        ));
+    }
+
+    Ok(url_base.to_string())
+}
+
+/// Zips the XCFramework in the package directory for hosting and removes it from the package
+///
+/// The archive `<xcframework name>.xcframework.zip` is written next to the package directory.
+pub(crate) fn archive_xcframework_with_output(
+    package_name: &str,
+    xcframework_name: &str,
+    url_base: &str,
+    config: &Config,
+) -> Result<RemoteBinaryTarget> {
+    let archive_name = format!("{xcframework_name}.xcframework.zip");
+    let archive_path = config.output_path(&archive_name);
+
+    let checksum = run_step(config, "Archiving XCFramework...", || {
+        let framework_dir = config
+            .output_path(package_name)
+            .join(format!("{xcframework_name}.xcframework"));
+
+        zip_xcframework(framework_dir.as_std_path(), archive_path.as_std_path())?;
+        fs::remove_dir_all(&framework_dir)
+            .with_context(|| format!("Could not remove {framework_dir} from the package"))?;
+
+        swiftpm_checksum(&archive_path)
+    })
+    .map_err(|e| {
+        anyhow!(
+            "Failed to archive XCFramework due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    let url = format!("{url_base}/{archive_name}");
+    info!(config, "Upload {archive_path} to {url} before publishing the package.");
+
+    Ok(RemoteBinaryTarget { url, checksum })
+}
+
+/// Writes the XCFramework at `framework_dir` into a zip archive with the framework as its only root entry
+///
+/// The archive only depends on the contents of the framework: entries are sorted, timestamps are
+/// fixed and permissions are normalized, so rebuilding unchanged sources keeps the checksum stable.
+fn zip_xcframework(framework_dir: &Path, archive_path: &Path) -> Result<()> {
+    let root = framework_dir
+        .parent()
+        .context("The XCFramework has no parent directory")?;
+
+    let file = File::create(archive_path)
+        .with_context(|| format!("Failed to create {}", archive_path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default());
+
+    add_to_zip(&mut zip, &options, framework_dir, root)
+        .with_context(|| format!("Failed to add files to {}", archive_path.display()))?;
+    zip.finish()
+        .with_context(|| format!("Failed to finalize {}", archive_path.display()))?;
+
+    Ok(())
+}
+
+fn add_to_zip<T: Write + Seek>(
+    zip: &mut ZipWriter<T>,
+    options: &FileOptions,
+    path: &Path,
+    root: &Path,
+) -> Result<()> {
+    // Zip entries always use forward slashes
+    let name = path
+        .strip_prefix(root)?
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.is_symlink() {
+        // Frameworks of macOS link their current version, e.g. Versions/Current -> A
+        let target = fs::read_link(path)?;
+        zip.add_symlink(name, target.to_string_lossy(), *options)?;
+    } else if metadata.is_dir() {
+        zip.add_directory(format!("{name}/"), options.unix_permissions(0o755))?;
+
+        let mut entries = fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            add_to_zip(zip, options, &entry.path(), root)?;
+        }
+    } else {
+        let permissions = if is_executable(&metadata) { 0o755 } else { 0o644 };
+        zip.start_file(name, options.unix_permissions(permissions))?;
+        let mut file = File::open(path)?;
+        std::io::copy(&mut file, zip)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &fs::Metadata) -> bool {
+    false
+}
+
+/// Computes the checksum SwiftPM verifies a binary target with, as `swift package compute-checksum` does
+fn swiftpm_checksum(archive_path: &Utf8Path) -> Result<String> {
+    let content =
+        fs::read(archive_path).with_context(|| format!("Could not read {archive_path}"))?;
+
+    Ok(format!("{:x}", Sha256::digest(&content)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    /// Creates a small XCFramework with a library and its headers below `dir`
+    fn fake_xcframework(dir: &Path) -> std::path::PathBuf {
+        let framework = dir.join("Demo.xcframework");
+        let library_dir = framework.join("ios-arm64");
+        fs::create_dir_all(library_dir.join("Headers")).unwrap();
+        fs::write(framework.join("Info.plist"), "<plist/>").unwrap();
+        fs::write(library_dir.join("libdemo.a"), "!<arch>\n").unwrap();
+        fs::write(library_dir.join("Headers/demoFFI.h"), "// header").unwrap();
+        framework
+    }
+
+    #[test]
+    fn archive_is_reproducible() {
+        let temp = tempfile::tempdir().unwrap();
+        let framework = fake_xcframework(temp.path());
+        let first = temp.path().join("first.zip");
+        let second = temp.path().join("second.zip");
+
+        zip_xcframework(&framework, &first).unwrap();
+        // Touching the files must not change the archive
+        std::thread::sleep(std::time::Duration::from_millis(1100));
+        fs::write(framework.join("Info.plist"), "<plist/>").unwrap();
+        #[cfg(unix)]
+        {
+            // Only the executable bit is kept, other permission bits are normalized
+            use std::os::unix::fs::PermissionsExt;
+            let library = framework.join("ios-arm64/libdemo.a");
+            fs::set_permissions(library, fs::Permissions::from_mode(0o600)).unwrap();
+        }
+        zip_xcframework(&framework, &second).unwrap();
+
+        assert_eq!(fs::read(&first).unwrap(), fs::read(&second).unwrap());
+    }
+
+    #[test]
+    fn archive_contains_the_framework_as_root_entry() {
+        let temp = tempfile::tempdir().unwrap();
+        let framework = fake_xcframework(temp.path());
+        let archive_path = temp.path().join("Demo.xcframework.zip");
+        zip_xcframework(&framework, &archive_path).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&archive_path).unwrap()).unwrap();
+        let names: Vec<_> = (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect();
+        assert_eq!(names[0], "Demo.xcframework/");
+        assert!(names.iter().all(|name| name.starts_with("Demo.xcframework/")), "{names:?}");
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).unwrap();
+            let expected = if entry.is_dir() { 0o755 } else { 0o644 };
+            assert_eq!(entry.unix_mode().unwrap() & 0o777, expected, "{}", entry.name());
+            let modified = entry.last_modified();
+            let fixed = DateTime::default();
+            assert_eq!(
+                (modified.datepart(), modified.timepart()),
+                (fixed.datepart(), fixed.timepart()),
+                "{}",
+                entry.name()
+            );
+
+            if entry.name() == "Demo.xcframework/ios-arm64/Headers/demoFFI.h" {
+                let mut content = String::new();
+                entry.read_to_string(&mut content).unwrap();
+                assert_eq!(content, "// header");
+            }
+        }
+    }
+
+    #[test]
+    fn checksum_is_the_sha256_of_the_archive() {
+        let temp = tempfile::tempdir().unwrap();
+        let archive_path = Utf8Path::from_path(temp.path()).unwrap().join("Demo.xcframework.zip");
+        fs::write(&archive_path, "abc").unwrap();
+
+        assert_eq!(
+            swiftpm_checksum(&archive_path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn url_base_must_use_https() {
+        assert_eq!(
+            validate_url_base("https://example.com/v1.0.0/").unwrap(),
+            "https://example.com/v1.0.0"
+        );
+        assert!(validate_url_base("http://example.com").is_err());
+        assert!(validate_url_base("https://").is_err());
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+use goblin::mach::constants::cputype::{
+    get_arch_name_from_types, CpuSubType, CpuType, CPU_SUBTYPE_MASK, CPU_TYPE_ARM, CPU_TYPE_ARM64,
+    CPU_TYPE_ARM64_32, CPU_TYPE_X86, CPU_TYPE_X86_64,
+};
+use goblin::mach::fat::FAT_MAGIC;
+use goblin::mach::Mach;
+use goblin::Object;
+
+use crate::Result;
+
+/// Size of the `fat_header` at the start of a universal binary
+const FAT_HEADER_SIZE: usize = 8;
+/// Size of each `fat_arch` record following the header
+const FAT_ARCH_SIZE: usize = 20;
+
+/// A single-architecture library merged into a universal binary
+struct Slice {
+    cputype: CpuType,
+    cpusubtype: CpuSubType,
+    align: u32,
+    content: Vec<u8>,
+}
+
+/// Merges the single-architecture libraries of `(triple, path)` pairs into the universal binary `output`
+///
+/// Works for static archives and dylibs like `lipo -create`. Every library must be built for the
+/// CPU type of its triple, and no two libraries may share a CPU type.
+pub(crate) fn create_universal_binary(libraries: &[(&str, &Path)], output: &Path) -> Result<()> {
+    let mut slices: Vec<Slice> = vec![];
+
+    for (triple, path) in libraries {
+        let expected = expected_cputype(triple)
+            .ok_or_else(|| anyhow!("Unsupported architecture of target {triple}"))?;
+
+        let content =
+            fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+        let (cputype, cpusubtype) = read_cputype(&content)
+            .with_context(|| format!("Could not read the architecture of {}", path.display()))?;
+
+        if cputype != expected {
+            return Err(anyhow!(
+                "{} was built for {} instead of {triple}",
+                path.display(),
+                arch_name(cputype, cpusubtype)
+            ));
+        }
+        if let Some(duplicate) = slices
+            .iter()
+            .find(|slice| slice.cputype == cputype && same_subtype(slice.cpusubtype, cpusubtype))
+        {
+            return Err(anyhow!(
+                "{} and another library are both built for {}, universal binaries need distinct architectures",
+                path.display(),
+                arch_name(duplicate.cputype, duplicate.cpusubtype)
+            ));
+        }
+
+        slices.push(Slice {
+            cputype,
+            cpusubtype,
+            align: alignment(cputype),
+            content,
+        });
+    }
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_fat_binary(&slices, output)
+        .with_context(|| format!("Failed to write universal binary {}", output.display()))
+}
+
+/// Returns the Mach-O CPU type of the architecture of `triple`
+fn expected_cputype(triple: &str) -> Option<CpuType> {
+    match triple.split('-').next()? {
+        "aarch64" | "arm64e" => Some(CPU_TYPE_ARM64),
+        "arm64_32" => Some(CPU_TYPE_ARM64_32),
+        "armv7k" | "armv7s" | "armv7" => Some(CPU_TYPE_ARM),
+        "x86_64" | "x86_64h" => Some(CPU_TYPE_X86_64),
+        "i386" | "i686" => Some(CPU_TYPE_X86),
+        _ => None,
+    }
+}
+
+/// Reads the CPU type and subtype of a thin Mach-O file or a static archive of Mach-O objects
+fn read_cputype(content: &[u8]) -> Result<(CpuType, CpuSubType)> {
+    match Object::parse(content)? {
+        Object::Mach(Mach::Binary(macho)) => Ok((macho.header.cputype, macho.header.cpusubtype)),
+        Object::Mach(Mach::Fat(_)) => Err(anyhow!("The library is already a universal binary")),
+        Object::Archive(archive) => {
+            let mut cputypes = vec![];
+            for member in archive.members() {
+                // The symbol table of the archive is not an object file
+                if member.starts_with("__.SYMDEF") {
+                    continue;
+                }
+                let bytes = archive.extract(member, content)?;
+                if let Ok(Object::Mach(Mach::Binary(macho))) = Object::parse(bytes) {
+                    let cputype = (macho.header.cputype, macho.header.cpusubtype);
+                    if !cputypes.contains(&cputype) {
+                        cputypes.push(cputype);
+                    }
+                }
+            }
+
+            match cputypes[..] {
+                [cputype] => Ok(cputype),
+                [] => Err(anyhow!("The archive does not contain any Mach-O objects")),
+                _ => Err(anyhow!(
+                    "The archive contains objects of different architectures"
+                )),
+            }
+        }
+        _ => Err(anyhow!(
+            "The library is neither a Mach-O file nor a static archive"
+        )),
+    }
+}
+
+/// Whether two subtypes describe the same architecture, ignoring the capability bits
+fn same_subtype(a: CpuSubType, b: CpuSubType) -> bool {
+    a & !CPU_SUBTYPE_MASK == b & !CPU_SUBTYPE_MASK
+}
+
+/// The power of 2 every slice of `cputype` is aligned to, matching the page size of the architecture
+fn alignment(cputype: CpuType) -> u32 {
+    match cputype {
+        CPU_TYPE_ARM | CPU_TYPE_ARM64 | CPU_TYPE_ARM64_32 => 14,
+        _ => 12,
+    }
+}
+
+fn arch_name(cputype: CpuType, cpusubtype: CpuSubType) -> String {
+    get_arch_name_from_types(cputype, cpusubtype)
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("CPU type {cputype:#x}"))
+}
+
+/// Writes the `fat_header`, one `fat_arch` per slice and the aligned slices, all big-endian
+fn write_fat_binary(slices: &[Slice], output: &Path) -> Result<()> {
+    let mut offset = FAT_HEADER_SIZE + FAT_ARCH_SIZE * slices.len();
+    let mut offsets = vec![];
+    for slice in slices {
+        offset = offset.next_multiple_of(1 << slice.align);
+        offsets.push(offset);
+        offset += slice.content.len();
+    }
+    if u32::try_from(offset).is_err() {
+        return Err(anyhow!(
+            "The libraries are too large for a universal binary with 32-bit offsets"
+        ));
+    }
+
+    let mut writer = BufWriter::new(File::create(output)?);
+    writer.write_all(&FAT_MAGIC.to_be_bytes())?;
+    writer.write_all(&(slices.len() as u32).to_be_bytes())?;
+    for (slice, offset) in slices.iter().zip(&offsets) {
+        writer.write_all(&slice.cputype.to_be_bytes())?;
+        writer.write_all(&slice.cpusubtype.to_be_bytes())?;
+        writer.write_all(&(*offset as u32).to_be_bytes())?;
+        writer.write_all(&(slice.content.len() as u32).to_be_bytes())?;
+        writer.write_all(&slice.align.to_be_bytes())?;
+    }
+
+    let mut position = FAT_HEADER_SIZE + FAT_ARCH_SIZE * slices.len();
+    for (slice, offset) in slices.iter().zip(&offsets) {
+        writer.write_all(&vec![0; offset - position])?;
+        writer.write_all(&slice.content)?;
+        position = offset + slice.content.len();
+    }
+    writer.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use goblin::mach::constants::cputype::{CPU_SUBTYPE_ARM64_ALL, CPU_SUBTYPE_X86_64_ALL};
+    use goblin::mach::header::{MH_MAGIC_64, MH_OBJECT};
+
+    /// Returns a thin 64-bit Mach-O object without load commands
+    fn thin_macho(cputype: CpuType, cpusubtype: CpuSubType) -> Vec<u8> {
+        [MH_MAGIC_64, cputype, cpusubtype, MH_OBJECT, 0, 0, 0, 0]
+            .iter()
+            .flat_map(|field| field.to_le_bytes())
+            .collect()
+    }
+
+    /// Returns a static archive containing `object` as its only member
+    fn archive(object: &[u8]) -> Vec<u8> {
+        let mut archive = b"!<arch>\n".to_vec();
+        archive.extend(
+            format!(
+                "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                "demo.o/",
+                0,
+                0,
+                0,
+                644,
+                object.len()
+            )
+            .bytes(),
+        );
+        archive.extend(object);
+        if object.len() % 2 == 1 {
+            archive.push(b'\n');
+        }
+        archive
+    }
+
+    fn be_u32(bytes: &[u8], offset: usize) -> u32 {
+        u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Writes the libraries into a temporary directory and merges them
+    fn merge(libraries: &[(&str, Vec<u8>)]) -> Result<Vec<u8>> {
+        let temp = tempfile::tempdir().unwrap();
+        let paths: Vec<_> = libraries
+            .iter()
+            .enumerate()
+            .map(|(i, (_, content))| {
+                let path = temp.path().join(format!("lib{i}.a"));
+                fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        let inputs: Vec<_> = libraries
+            .iter()
+            .zip(&paths)
+            .map(|((triple, _), path)| (*triple, path.as_path()))
+            .collect();
+
+        let output = temp.path().join("universal").join("libdemo.a");
+        create_universal_binary(&inputs, &output)?;
+        Ok(fs::read(output).unwrap())
+    }
+
+    #[test]
+    fn writes_aligned_big_endian_fat_header() {
+        let arm64 = archive(&thin_macho(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL));
+        let x86_64 = thin_macho(CPU_TYPE_X86_64, CPU_SUBTYPE_X86_64_ALL);
+        let fat = merge(&[
+            ("aarch64-apple-ios-sim", arm64.clone()),
+            ("x86_64-apple-ios", x86_64.clone()),
+        ])
+        .unwrap();
+
+        assert_eq!(&fat[0..4], &[0xca, 0xfe, 0xba, 0xbe]);
+        assert_eq!(be_u32(&fat, 0), FAT_MAGIC);
+        assert_eq!(be_u32(&fat, 4), 2);
+
+        let slices = [
+            (CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL, 14, &arm64),
+            (CPU_TYPE_X86_64, CPU_SUBTYPE_X86_64_ALL, 12, &x86_64),
+        ];
+        for (i, (cputype, cpusubtype, align, content)) in slices.into_iter().enumerate() {
+            let record = FAT_HEADER_SIZE + FAT_ARCH_SIZE * i;
+            let offset = be_u32(&fat, record + 8) as usize;
+            let size = be_u32(&fat, record + 12) as usize;
+
+            assert_eq!(be_u32(&fat, record), cputype);
+            assert_eq!(be_u32(&fat, record + 4), cpusubtype);
+            assert_eq!(be_u32(&fat, record + 16), align);
+            assert_eq!(
+                offset % (1 << align),
+                0,
+                "slice {i} is not aligned to 2^{align}"
+            );
+            assert_eq!(size, content.len());
+            assert_eq!(&fat[offset..offset + size], content.as_slice());
+        }
+
+        // The x86_64 slice directly follows the page-aligned arm64 slice
+        assert_eq!(be_u32(&fat, FAT_HEADER_SIZE + 8), 1 << 14);
+        assert_eq!(
+            be_u32(&fat, FAT_HEADER_SIZE + FAT_ARCH_SIZE + 8),
+            (1 << 14) + (1 << 12)
+        );
+        assert_eq!(fat.len(), (1 << 14) + (1 << 12) + x86_64.len());
+        assert!(matches!(Mach::parse(&fat), Ok(Mach::Fat(fat)) if fat.narches == 2));
+    }
+
+    #[test]
+    fn rejects_duplicate_architectures() {
+        let arm64 = thin_macho(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL);
+        let error = merge(&[
+            ("aarch64-apple-darwin", arm64.clone()),
+            ("aarch64-apple-ios", archive(&arm64)),
+        ])
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.contains("distinct architectures"), "{error}");
+    }
+
+    #[test]
+    fn rejects_library_built_for_another_triple() {
+        let error = merge(&[
+            (
+                "aarch64-apple-darwin",
+                thin_macho(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL),
+            ),
+            (
+                "x86_64-apple-darwin",
+                thin_macho(CPU_TYPE_ARM64, CPU_SUBTYPE_ARM64_ALL),
+            ),
+        ])
+        .unwrap_err()
+        .to_string();
+
+        assert!(error.contains("instead of x86_64-apple-darwin"), "{error}");
+    }
+
+    #[test]
+    fn rejects_non_macho_libraries() {
+        let result = merge(&[("aarch64-apple-darwin", b"not a library".to_vec())]);
+        assert!(result.is_err());
+    }
+}
@@ -1,44 +1,59 @@
 use anyhow::{anyhow, Context, Result};
+use camino::Utf8Path;
+use std::collections::HashMap;
 use cargo_metadata::Package;
 use convert_case::{Case, Casing};
 use dialoguer::{Input, MultiSelect};
 
 use crate::apple::apple_target::{ApplePlatform, AppleTarget};
+use crate::apple::distribution::{archive_xcframework_with_output, validate_url_base, Distribution};
 use crate::common::{
-    metadata::{metadata, MetadataExt},
-    models::{Config, FeatureOptions, LibType, Mode},
+    metadata::{library_target, Workspace},
+    models::{Config, CrateSelection, FeatureArgs, FeatureOptions, LibType, Mode},
+    project::{AppleConfig, ProjectConfig},
+    toolchain::ensure_targets_installed,
 };
-use crate::console::{messages::*, step::run_step_with_commands, theme::prompt_theme};
+use crate::console::{messages::*, step::{run_step, run_step_with_commands}, theme::prompt_theme};
 use crate::ffi::swift::generate_swift_bindings_with_output;
-use crate::apple::swiftpackage::{create_package_with_output, recreate_output_dir};
+use crate::apple::swiftpackage::{create_package_with_output, recreate_output_dir, PackageManifest};
 use crate::apple::xcframework::create_xcframework_with_output;
 
 #[allow(clippy::too_many_arguments)]
 pub fn build_swift_package(
+    workspace: &Workspace,
+    selection: &CrateSelection,
     platforms: Option<Vec<ApplePlatform>>,
     build_target: Option<&str>,
     package_name: Option<String>,
-    xcframework_name: String,
-    disable_warnings: bool,
+    xcframework_name: Option<String>,
+    deployment_targets: Vec<(ApplePlatform, String)>,
+    swift_tools_version: Option<String>,
+    distribution: Option<Distribution>,
+    url_base: Option<String>,
+    apple_silicon_only: Option<bool>,
+    disable_warnings: Option<bool>,
     config: Config,
-    mode: Mode,
-    lib_type: LibType,
-    features: FeatureOptions,
+    mode: Option<Mode>,
+    lib_type: Option<LibType>,
+    features: FeatureArgs,
 ) -> Result<()> {
-    // TODO: Allow path as optional argument to take other directories than current directory
-    // let crates = metadata().uniffi_crates();
-    let crates = [metadata()
-        .current_crate()
-        .context("Current directory is not part of a crate!")?];
+    let crates = workspace.selected_crates(selection)?;
 
     if crates.len() == 1 {
         return build_swift_package_for_crate(
+            workspace,
             crates[0],
             platforms.clone(),
             build_target,
             package_name,
             xcframework_name,
+            deployment_targets,
+            swift_tools_version,
+            distribution,
+            url_base,
+            apple_silicon_only,
             disable_warnings,
+            false,
             &config,
             mode,
             lib_type,
@@ -50,59 +65,107 @@ pub fn build_swift_package(
         ));
     }
 
+    // Remote archives are written next to the packages, so every crate needs its own name
+    let silent = Config {
+        silent: true,
+        ..config.clone()
+    };
+    let mut archives: HashMap<String, &str> = HashMap::new();
+    for current_crate in &crates {
+        let project = ProjectConfig::load(current_crate, &silent)?;
+        if resolve_distribution(distribution, &project.apple) != Distribution::Remote {
+            continue;
+        }
+
+        let name = resolve_xcframework_name(xcframework_name.clone(), &project.apple, current_crate, true);
+        if let Some(other) = archives.insert(name.clone(), &current_crate.name) {
+            return Err(anyhow!(
+                "Both {other} and {} would be archived as {name}.xcframework.zip, configure a distinct xcframework-name for each crate!",
+                current_crate.name
+            ));
+        }
+    }
+
     crates
         .iter()
         .map(|current_crate| {
             info!(&config, "Packaging crate {}", current_crate.name);
             build_swift_package_for_crate(
+                workspace,
                 current_crate,
                 platforms.clone(),
                 build_target,
                 None,
                 xcframework_name.clone(),
+                deployment_targets.clone(),
+                swift_tools_version.clone(),
+                distribution,
+                url_base.clone(),
+                apple_silicon_only,
                 disable_warnings,
+                true,
                 &config,
                 mode,
-                lib_type.clone(),
+                lib_type,
                 features.clone(),
             )
         })
         .filter_map(|result| result.err())
         .collect::<Vec<_>>()
         .into_iter()
-        .fold(Ok(()), |acc, err| {
-            if let Err(e) = acc {
-                Err(e.context(err))
-            } else {
-                Err(err)
-            }
-        })
+        .reduce(|acc, err| acc.context(err))
+        .map_or(Ok(()), Err)
 }
 
 #[allow(clippy::too_many_arguments)]
 fn build_swift_package_for_crate(
+    workspace: &Workspace,
     current_crate: &Package,
     platforms: Option<Vec<ApplePlatform>>,
     build_target: Option<&str>,
     package_name: Option<String>,
-    xcframework_name: String,
-    disable_warnings: bool,
+    xcframework_name: Option<String>,
+    deployment_targets: Vec<(ApplePlatform, String)>,
+    swift_tools_version: Option<String>,
+    distribution: Option<Distribution>,
+    url_base: Option<String>,
+    apple_silicon_only: Option<bool>,
+    disable_warnings: Option<bool>,
+    multiple_crates: bool,
     config: &Config,
-    mode: Mode,
-    lib_type: LibType,
-    features: FeatureOptions,
+    mode: Option<Mode>,
+    lib_type: Option<LibType>,
+    features: FeatureArgs,
 ) -> Result<()> {
-    let lib = current_crate
-        .targets
-        .iter()
-        .find(|t| t.kind.contains(&"lib".to_owned()))
-        .context("No library tag defined in Cargo.toml!")?;
+    let lib = library_target(current_crate)?;
+
+    let project = ProjectConfig::load(current_crate, config)?;
+    let mode = project.mode(mode);
+    let features = project.features(features);
+    let lib_type = lib_type.or(project.apple.lib_type).unwrap_or(LibType::Static);
+    let xcframework_name =
+        resolve_xcframework_name(xcframework_name, &project.apple, current_crate, multiple_crates);
+    let disable_warnings = disable_warnings.unwrap_or(project.apple.suppress_warnings);
+    let apple_silicon_only = apple_silicon_only.unwrap_or(project.apple.apple_silicon_only);
+    let distribution = resolve_distribution(distribution, &project.apple);
+    let url_base = match distribution {
+        Distribution::Local => None,
+        Distribution::Remote => {
+            let url_base = url_base.or(project.apple.url_base).ok_or_else(|| {
+                anyhow!("A URL base the XCFramework will be hosted at is required for remote distribution!")
+            })?;
+            Some(validate_url_base(&url_base)?)
+        }
+    };
 
     let crate_name = current_crate.name.to_lowercase();
-    let package_name =
-        package_name.unwrap_or_else(|| prompt_package_name(&crate_name, config.accept_all));
+    let package_name = package_name
+        .or(project.apple.package_name)
+        .unwrap_or_else(|| prompt_package_name(&crate_name, config.accept_all));
 
-    let platforms = platforms.unwrap_or_else(|| prompt_platforms(config.accept_all));
+    let platforms = platforms
+        .or(project.apple.platforms)
+        .unwrap_or_else(|| prompt_platforms(config.accept_all));
 
     if platforms.is_empty() {
         return Err(anyhow!("At least 1 platform needs to be selected!"));
@@ -118,30 +181,58 @@ fn build_swift_package_for_crate(
     let mut targets: Vec<_> = platforms
         .into_iter()
         .flat_map(|p| p.into_apple_platform_target())
-        .map(|p| p.target())
+        .map(|p| p.target(apple_silicon_only))
         .collect();
 
     if let Some(build_target) = build_target {
-        targets.retain_mut(|platform_target| match platform_target {
-            AppleTarget { architectures, .. } => {
-                architectures.iter().any(|arch| *arch == build_target)
-            }
-        });
+        targets.retain(|platform_target| platform_target.architectures().contains(&build_target));
         if targets.is_empty() {
             return Err(anyhow!("No matching build target for {}", build_target));
         }
     }
 
+    // Only the platforms that are actually built are declared, command line values come first
+    let built_platforms: Vec<_> = targets.iter().map(|t| t.platform().platform).collect();
+    let deployment_targets: Vec<_> = deployment_targets
+        .into_iter()
+        .chain(project.apple.deployment_targets.unwrap_or_default())
+        .collect();
+    let manifest = PackageManifest::new(
+        &built_platforms,
+        &deployment_targets,
+        swift_tools_version.or(project.apple.swift_tools_version),
+    )?;
+
+    // The standard library of tier 3 targets is built from source and cannot be installed
+    let installable: Vec<_> = targets
+        .iter()
+        .filter(|t| !t.platform().platform.is_tier_3())
+        .flat_map(|t| t.architectures().iter().copied())
+        .collect();
+    ensure_targets_installed(&installable, config)?;
+
+    let target_dir = workspace.target_dir();
     let crate_name = lib.name.replace('-', "_");
     for target in &targets {
-        build_with_output(target, &crate_name, mode, lib_type, config, &features)?;
+        build_with_output(
+            target,
+            &current_crate.manifest_path,
+            &target_dir,
+            &crate_name,
+            mode,
+            lib_type,
+            config,
+            &features,
+        )?;
     }
 
-    generate_swift_bindings_with_output(&targets, &crate_name, mode, lib_type, config)?;
+    generate_swift_bindings_with_output(workspace, &targets, &crate_name, mode, lib_type, config)?;
 
-    recreate_output_dir(&package_name).context("Could not create package output directory!")?;
+    recreate_output_dir(&config.output_path(&package_name))
+        .context("Could not create package output directory!")?;
     create_xcframework_with_output(
         &targets,
+        &target_dir,
         &crate_name,
         &package_name,
         &xcframework_name,
@@ -149,11 +240,48 @@ fn build_swift_package_for_crate(
         lib_type,
         config,
     )?;
-    create_package_with_output(&package_name, &xcframework_name, disable_warnings, config)?;
+    let remote_binary = url_base
+        .map(|url_base| {
+            archive_xcframework_with_output(&package_name, &xcframework_name, &url_base, config)
+        })
+        .transpose()?;
+    create_package_with_output(
+        &package_name,
+        &xcframework_name,
+        &manifest,
+        remote_binary.as_ref(),
+        disable_warnings,
+        config,
+    )?;
 
     Ok(())
 }
 
+/// Returns the XCFramework name, named after the crate by default when packaging several crates
+fn resolve_xcframework_name(
+    xcframework_name: Option<String>,
+    project: &AppleConfig,
+    current_crate: &Package,
+    multiple_crates: bool,
+) -> String {
+    xcframework_name
+        .or_else(|| project.xcframework_name.clone())
+        .unwrap_or_else(|| {
+            // The crate name alone would clash with the Swift target of the package
+            if multiple_crates {
+                format!("{}Framework", current_crate.name.to_case(Case::UpperCamel))
+            } else {
+                "RustFramework".to_string()
+            }
+        })
+}
+
+fn resolve_distribution(distribution: Option<Distribution>, project: &AppleConfig) -> Distribution {
+    distribution
+        .or(project.distribution)
+        .unwrap_or(Distribution::Local)
+}
+
 fn prompt_platforms(accept_all: bool) -> Vec<ApplePlatform> {
     let platforms = ApplePlatform::all();
     let items = platforms.map(|p| p.display_name());
@@ -190,15 +318,18 @@ fn prompt_package_name(crate_name: &str, accept_all: bool) -> String {
         .unwrap()
 }
 
+#[allow(clippy::too_many_arguments)]
 fn build_with_output(
     target: &AppleTarget,
+    manifest_path: &Utf8Path,
+    target_dir: &Utf8Path,
     lib_name: &str,
     mode: Mode,
     lib_type: LibType,
     config: &Config,
     features: &FeatureOptions,
 ) -> Result<()> {
-    let mut commands = target.commands(lib_name, mode, lib_type, features);
+    let mut commands = target.commands(manifest_path, mode, features);
     for command in &mut commands {
         command.env("CARGO_TERM_COLOR", "always");
     }
@@ -209,5 +340,23 @@ fn build_with_output(
         &mut commands,
     )?;
 
+    if target.architectures().len() > 1 {
+        run_step(
+            config,
+            format!("Creating universal library for {}", target.display_name()),
+            || target.create_universal_library(target_dir, lib_name, mode, lib_type),
+        )?;
+    }
+
+    let mut install_name_commands =
+        target.rpath_install_id_commands(target_dir, lib_name, mode, lib_type);
+    if !install_name_commands.is_empty() {
+        run_step_with_commands(
+            config,
+            format!("Setting install name for {}", target.display_name()),
+            &mut install_name_commands,
+        )?;
+    }
+
     Ok(())
 }
@@ -1,27 +1,122 @@
+use crate::apple::apple_target::ApplePlatform;
 use crate::common::models::Config;
 use crate::console::step::run_step;
 use crate::console::{MainSpinner, Ticking};
+use crate::templating::{RemoteBinaryTarget, SwiftPlatform};
 use crate::{path::recreate_dir, templating, Context, Result};
+use anyhow::anyhow;
 use askama::Template;
+use camino::Utf8Path;
 use glob::glob;
 use std::fs::{copy, create_dir_all, write};
 use std::ops::Not;
 
+/// swift-tools-version of the Package.swift if none is configured and no platform requires a newer one
+const DEFAULT_SWIFT_TOOLS_VERSION: &str = "5.5";
+
+/// Settings of the generated Package.swift
+#[derive(Debug, Clone)]
+pub(crate) struct PackageManifest {
+    pub(crate) swift_tools_version: String,
+    pub(crate) platforms: Vec<SwiftPlatform>,
+}
+
+impl PackageManifest {
+    /// Declares every platform in `platforms` with its deployment target
+    ///
+    /// The first entry of `deployment_targets` for a platform is used, otherwise its default. Without
+    /// a configured swift-tools-version, the oldest one supporting all platforms is chosen.
+    pub(crate) fn new(
+        platforms: &[ApplePlatform],
+        deployment_targets: &[(ApplePlatform, String)],
+        swift_tools_version: Option<String>,
+    ) -> Result<Self> {
+        let required_version = platforms
+            .iter()
+            .map(|platform| platform.min_swift_tools_version())
+            .chain([DEFAULT_SWIFT_TOOLS_VERSION])
+            .max_by_key(|version| parse_version(version))
+            .unwrap_or(DEFAULT_SWIFT_TOOLS_VERSION);
+
+        let swift_tools_version = match swift_tools_version {
+            Some(version) if parse_version(&version).is_none() => {
+                return Err(anyhow!(
+                    "Invalid swift-tools-version \"{version}\"! Expected a version like 5.9."
+                ));
+            }
+            Some(version) if parse_version(&version) < parse_version(required_version) => {
+                return Err(anyhow!(
+                    "swift-tools-version {version} is too old for the selected platforms, at least {required_version} is required!"
+                ));
+            }
+            Some(version) => version,
+            None => required_version.to_string(),
+        };
+
+        let mut swift_platforms: Vec<SwiftPlatform> = vec![];
+        for platform in platforms {
+            let version = deployment_targets
+                .iter()
+                .find(|(configured, _)| configured == platform)
+                .map(|(_, version)| version.as_str())
+                .unwrap_or_else(|| platform.default_deployment_target());
+            if parse_version(version).is_none() {
+                return Err(anyhow!(
+                    "Invalid deployment target \"{version}\" for {}! Expected a version like 15.0.",
+                    platform.swift_platform_name()
+                ));
+            }
+
+            let name = platform.swift_platform_name();
+            if !swift_platforms.iter().any(|p| p.name == name) {
+                swift_platforms.push(SwiftPlatform {
+                    name,
+                    version: version.to_string(),
+                });
+            }
+        }
+
+        Ok(Self {
+            swift_tools_version,
+            platforms: swift_platforms,
+        })
+    }
+}
+
+/// Parses a version like `5.9` or `10.15.1` into its components
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    let components: Option<Vec<u32>> = version.split('.').map(|c| c.parse().ok()).collect();
+    components.filter(|components| (1..=3).contains(&components.len()))
+}
+
 pub(crate) fn create_package_with_output(
     package_name: &str,
     xcframework_name: &str,
+    manifest: &PackageManifest,
+    remote_binary: Option<&RemoteBinaryTarget>,
     disable_warnings: bool,
     config: &Config,
 ) -> Result<()> {
     run_step(
         config,
         format!("Creating Swift Package '{package_name}'..."),
-        || create_swiftpackage(package_name, xcframework_name, disable_warnings),
+        || {
+            create_swiftpackage(
+                package_name,
+                xcframework_name,
+                manifest,
+                remote_binary,
+                disable_warnings,
+                &config.generated_dir(),
+                &config.output_path(package_name),
+            )
+        },
     )?;
 
     let spinner = config.silent.not().then(|| {
         MainSpinner::with_message(format!(
-            "Successfully created Swift Package in '{package_name}/'!"
+            "Successfully created Swift Package in '{}/'!",
+            config.output_path(package_name)
         ))
     });
     spinner.finish();
@@ -31,30 +126,38 @@ pub(crate) fn create_package_with_output(
 
 /// Create artifacts for a swift package given the package name
 ///
-/// **Note**: This method assumes that a directory with the package name and the .xcframework already exists
-pub fn create_swiftpackage(
+/// **Note**: This method assumes that the package directory and, unless it is hosted remotely, the
+/// .xcframework already exist
+pub(crate) fn create_swiftpackage(
     package_name: &str,
     xcframework_name: &str,
+    manifest: &PackageManifest,
+    remote_binary: Option<&RemoteBinaryTarget>,
     disable_warnings: bool,
+    generated_dir: &Utf8Path,
+    package_dir: &Utf8Path,
 ) -> Result<()> {
     let package_manifest = templating::PackageSwift {
+        swift_tools_version: &manifest.swift_tools_version,
         package_name,
+        platforms: &manifest.platforms,
         xcframework_name,
+        remote_binary,
         disable_warnings,
     };
 
     write(
-        format!("{}/Package.swift", package_name),
+        package_dir.join("Package.swift"),
         package_manifest
             .render()
             .context("Failed to render Package.swift template")?,
     )
     .context("Could not write Package.swift")?;
 
-    create_dir_all(format!("{}/Sources/{}", package_name, package_name))
-        .context("Could not create module sources directory")?;
+    let sources_dir = package_dir.join("Sources").join(package_name);
+    create_dir_all(&sources_dir).context("Could not create module sources directory")?;
 
-    for swift_file in glob("./generated/sources/*.swift")
+    for swift_file in glob(generated_dir.join("sources/*.swift").as_str())
         .context("Could not find generated swift source files")?
     {
         let swift_file = swift_file.context("Could not access generated swift source file")?;
@@ -66,7 +169,7 @@ pub fn create_swiftpackage(
             .to_string();
         copy(
             swift_file,
-            format!("{}/Sources/{}/{}", package_name, package_name, file_name),
+            sources_dir.join(file_name),
         )
         .context("Could not copy generated swift source files")?;
     }
@@ -74,7 +177,152 @@ pub fn create_swiftpackage(
     Ok(())
 }
 
-pub fn recreate_output_dir(package_name: &str) -> Result<()> {
-    let dir = format!("./{package_name}");
-    recreate_dir(dir)
+pub fn recreate_output_dir(package_dir: &Utf8Path) -> Result<()> {
+    recreate_dir(package_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(manifest: &PackageManifest, remote_binary: Option<&RemoteBinaryTarget>) -> String {
+        templating::PackageSwift {
+            swift_tools_version: &manifest.swift_tools_version,
+            package_name: "Demo",
+            platforms: &manifest.platforms,
+            xcframework_name: "DemoFFI",
+            remote_binary,
+            disable_warnings: remote_binary.is_some(),
+        }
+        .render()
+        .unwrap()
+    }
+
+    #[test]
+    fn renders_ios_and_macos_with_default_deployment_targets() {
+        let manifest =
+            PackageManifest::new(&[ApplePlatform::IOS, ApplePlatform::MacOS], &[], None).unwrap();
+
+        assert_eq!(
+            render(&manifest, None),
+            r#"// swift-tools-version:5.5
+import PackageDescription;
+
+let package = Package(
+    name: "Demo",
+    platforms: [
+        .iOS("13.0"),
+        .macOS("10.15")
+    ],
+    products: [
+        .library(
+            name: "Demo",
+            targets: ["Demo"]
+        )
+    ],
+    dependencies: [ ],
+    targets: [
+        .binaryTarget(name: "DemoFFI", path: "./DemoFFI.xcframework"),
+        .target(
+            name: "Demo",
+            dependencies: [
+                .target(name: "DemoFFI")
+            ]
+        ),
+    ]
+)"#
+        );
+    }
+
+    #[test]
+    fn renders_experimental_platforms_with_custom_deployment_targets() {
+        let manifest = PackageManifest::new(
+            &[ApplePlatform::TvOS, ApplePlatform::WatchOS, ApplePlatform::VisionOS],
+            &[
+                (ApplePlatform::TvOS, "17.0".to_string()),
+                (ApplePlatform::VisionOS, "1.1".to_string()),
+                (ApplePlatform::TvOS, "16.0".to_string()),
+            ],
+            None,
+        )
+        .unwrap();
+        let remote_binary = RemoteBinaryTarget {
+            url: "https://example.com/DemoFFI.xcframework.zip".to_string(),
+            checksum: "0123456789abcdef".to_string(),
+        };
+
+        assert_eq!(
+            render(&manifest, Some(&remote_binary)),
+            r#"// swift-tools-version:5.9
+import PackageDescription;
+
+let package = Package(
+    name: "Demo",
+    platforms: [
+        .tvOS("17.0"),
+        .watchOS("6.0"),
+        .visionOS("1.1")
+    ],
+    products: [
+        .library(
+            name: "Demo",
+            targets: ["Demo"]
+        )
+    ],
+    dependencies: [ ],
+    targets: [
+        .binaryTarget(
+            name: "DemoFFI",
+            url: "https://example.com/DemoFFI.xcframework.zip",
+            checksum: "0123456789abcdef"
+        ),
+        .target(
+            name: "Demo",
+            dependencies: [
+                .target(name: "DemoFFI")
+            ],
+            swiftSettings: [
+                .unsafeFlags(["-suppress-warnings"]),
+            ]
+        ),
+    ]
+)"#
+        );
+    }
+
+    #[test]
+    fn configured_swift_tools_version_is_used() {
+        let manifest =
+            PackageManifest::new(&[ApplePlatform::IOS], &[], Some("5.10".to_string())).unwrap();
+        assert!(render(&manifest, None).starts_with("// swift-tools-version:5.10\n"));
+    }
+
+    #[test]
+    fn rejects_invalid_swift_tools_version() {
+        for version in ["", "five", "5.9-beta", "5.9.0.1"] {
+            let result = PackageManifest::new(&[ApplePlatform::IOS], &[], Some(version.to_string()));
+            assert!(result.is_err(), "{version}");
+        }
+    }
+
+    #[test]
+    fn rejects_too_old_swift_tools_version() {
+        let error = PackageManifest::new(&[ApplePlatform::VisionOS], &[], Some("5.5".to_string()))
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("at least 5.9 is required"), "{error}");
+
+        // 5.10 is newer than 5.9 even though it sorts before it as a string
+        assert!(PackageManifest::new(&[ApplePlatform::VisionOS], &[], Some("5.10".to_string())).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_deployment_target() {
+        let result = PackageManifest::new(
+            &[ApplePlatform::IOS],
+            &[(ApplePlatform::IOS, "latest".to_string())],
+            None,
+        );
+        assert!(result.is_err());
+    }
 }
@@ -1,13 +1,22 @@
 use anyhow::{Context, Result};
-use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use askama::Template;
+use camino::Utf8Path;
+use std::fs::{self, create_dir_all};
+use std::path::Path;
 
-use crate::apple::apple_target::AppleTarget;
+use crate::apple::apple_target::{library_file_name, AppleTarget};
 use crate::common::models::{Config, LibType, Mode};
+use crate::common::path::{copy_dir, recreate_dir};
+use crate::common::templating::{XcframeworkInfoPlist, XcframeworkLibrary};
 use crate::console::step::run_step;
 
+/// Directory the headers of every library are copied to, next to the library
+const HEADERS_DIR: &str = "Headers";
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn create_xcframework_with_output(
     targets: &[AppleTarget],
+    target_dir: &Utf8Path,
     lib_name: &str,
     package_name: &str,
     xcframework_name: &str,
@@ -16,13 +25,12 @@ pub(crate) fn create_xcframework_with_output(
     config: &Config,
 ) -> Result<()> {
     run_step(config, "Creating XCFramework...", || {
-        // TODO: show command spinner here with xcbuild command
-        let output_dir = PathBuf::from(package_name);
-        // TODO: make this configurable
-        let generated_dir = PathBuf::from("./generated");
+        let output_dir = config.output_path(package_name).into_std_path_buf();
+        let generated_dir = config.generated_dir().into_std_path_buf();
 
         create_xcframework(
             targets,
+            target_dir,
             lib_name,
             xcframework_name,
             &generated_dir,
@@ -39,8 +47,15 @@ pub(crate) fn create_xcframework_with_output(
     })
 }
 
+/// Assembles the XCFramework `<output_dir>/<xcframework_name>.xcframework` from the built libraries
+///
+/// Every target becomes a library directory named by its identifier, containing the library and a
+/// copy of the generated headers, and is described in the `Info.plist`. Unlike
+/// `xcodebuild -create-xcframework`, this does not require Xcode.
+#[allow(clippy::too_many_arguments)]
 pub fn create_xcframework(
     targets: &[AppleTarget],
+    target_dir: &Utf8Path,
     lib_name: &str,
     xcframework_name: &str,
     generated_dir: &Path,
@@ -48,46 +63,178 @@ pub fn create_xcframework(
     mode: Mode,
     lib_type: LibType,
 ) -> Result<()> {
-    let libs: Vec<_> = targets
-        .iter()
-        .map(|t| t.library_path(lib_name, mode, lib_type))
-        .collect();
-
     let headers = generated_dir.join("headers");
-    let headers = headers
-        .to_str()
-        .context("Directory for bindings has an invalid name")?;
+    let framework = output_dir.join(format!("{xcframework_name}.xcframework"));
+    recreate_dir(&framework)?;
+
+    let mut libraries: Vec<XcframeworkLibrary> = vec![];
+    for target in targets {
+        let identifier = target.library_identifier();
+        if libraries.iter().any(|library| library.identifier == identifier) {
+            anyhow::bail!(
+                "Both {} and another target would be packaged as {identifier}, an XCFramework can only contain one library per platform",
+                target.display_name()
+            );
+        }
+
+        let library_dir = framework.join(&identifier);
+        create_dir_all(&library_dir)?;
+
+        let library_file = library_file_name(lib_name, lib_type);
+        let library_path = target.library_path(target_dir, lib_name, mode, lib_type);
+        fs::copy(&library_path, library_dir.join(&library_file))
+            .with_context(|| format!("Failed to copy library for {}", target.display_name()))?;
+        copy_dir(&headers, &library_dir.join(HEADERS_DIR))
+            .with_context(|| format!("Failed to copy headers for {}", target.display_name()))?;
+
+        libraries.push(XcframeworkLibrary {
+            identifier,
+            library_path: library_file,
+            headers_path: HEADERS_DIR,
+            architectures: target.apple_architectures(),
+            platform: target.platform().platform.xcframework_platform(),
+            platform_variant: target.platform_variant(),
+        });
+    }
+
+    let info_plist = XcframeworkInfoPlist {
+        libraries: &libraries,
+    };
+    fs::write(
+        framework.join("Info.plist"),
+        info_plist
+            .render()
+            .context("Failed to render Info.plist template")?,
+    )
+    .context("Failed to write Info.plist")?;
+
+    Ok(())
+}
 
-    let output_dir_name = &output_dir
-        .to_str()
-        .context("Output directory has an invalid name")?;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apple::apple_target::{ApplePlatform, ApplePlatformTarget};
 
-    let framework = format!("{output_dir_name}/{xcframework_name}.xcframework");
+    fn target(platform: ApplePlatform, is_simulator: bool) -> AppleTarget {
+        ApplePlatformTarget {
+            platform,
+            is_simulator,
+        }
+        .target(false)
+    }
 
-    let mut xcodebuild = Command::new("xcodebuild");
-    xcodebuild.arg("-create-xcframework");
+    /// Writes a dummy library for every target and the generated headers
+    fn fake_build(targets: &[AppleTarget], target_dir: &Utf8Path, generated_dir: &Path) {
+        for target in targets {
+            let library_path = target.library_path(target_dir, "demo", Mode::Release, LibType::Static);
+            let library_path = Path::new(&library_path);
+            create_dir_all(library_path.parent().unwrap()).unwrap();
+            fs::write(library_path, target.display_name()).unwrap();
+        }
 
-    for lib in &libs {
-        xcodebuild.arg("-library");
-        xcodebuild.arg(lib);
-        xcodebuild.arg("-headers");
-        xcodebuild.arg(headers);
+        let headers = generated_dir.join("headers");
+        create_dir_all(&headers).unwrap();
+        fs::write(headers.join("demoFFI.h"), "// header").unwrap();
+        fs::write(headers.join("module.modulemap"), "module demoFFI {}").unwrap();
     }
 
-    let output = xcodebuild
-        .arg("-output")
-        .arg(&framework)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
-        .context("Failed to execute xcodebuild command")?;
-
-    if !output.status.success() {
-        anyhow::bail!(
-            "xcodebuild command failed: {}",
-            String::from_utf8_lossy(&output.stderr)
+    #[test]
+    fn assembles_xcframework_without_xcode() {
+        let temp = tempfile::tempdir().unwrap();
+        let target_dir = Utf8Path::from_path(temp.path()).unwrap().join("target");
+        let generated_dir = temp.path().join("generated");
+        let output_dir = temp.path().join("out");
+
+        let targets = vec![
+            target(ApplePlatform::IOS, false),
+            target(ApplePlatform::IOS, true),
+            target(ApplePlatform::MacOS, false),
+        ];
+        fake_build(&targets, &target_dir, &generated_dir);
+
+        create_xcframework(
+            &targets,
+            &target_dir,
+            "demo",
+            "Demo",
+            &generated_dir,
+            &output_dir,
+            Mode::Release,
+            LibType::Static,
         )
-    } else {
-        Ok(())
+        .unwrap();
+
+        let framework = output_dir.join("Demo.xcframework");
+        for identifier in ["ios-arm64", "ios-arm64_x86_64-simulator", "macos-arm64_x86_64"] {
+            let library_dir = framework.join(identifier);
+            assert!(library_dir.join("libdemo.a").is_file(), "{identifier}");
+            assert!(library_dir.join("Headers/demoFFI.h").is_file(), "{identifier}");
+            assert!(library_dir.join("Headers/module.modulemap").is_file(), "{identifier}");
+        }
+        assert_eq!(
+            fs::read_to_string(framework.join("ios-arm64_x86_64-simulator/libdemo.a")).unwrap(),
+            "iOS Simulator"
+        );
+
+        let info_plist = fs::read_to_string(framework.join("Info.plist")).unwrap();
+        let expected_simulator = r#"		<dict>
+			<key>BinaryPath</key>
+			<string>libdemo.a</string>
+			<key>HeadersPath</key>
+			<string>Headers</string>
+			<key>LibraryIdentifier</key>
+			<string>ios-arm64_x86_64-simulator</string>
+			<key>LibraryPath</key>
+			<string>libdemo.a</string>
+			<key>SupportedArchitectures</key>
+			<array>
+				<string>arm64</string>
+				<string>x86_64</string>
+			</array>
+			<key>SupportedPlatform</key>
+			<string>ios</string>
+			<key>SupportedPlatformVariant</key>
+			<string>simulator</string>
+		</dict>"#;
+        assert!(info_plist.contains(expected_simulator), "{info_plist}");
+
+        let expected_device = r#"			<key>LibraryIdentifier</key>
+			<string>ios-arm64</string>
+			<key>LibraryPath</key>
+			<string>libdemo.a</string>
+			<key>SupportedArchitectures</key>
+			<array>
+				<string>arm64</string>
+			</array>
+			<key>SupportedPlatform</key>
+			<string>ios</string>
+		</dict>"#;
+        assert!(info_plist.contains(expected_device), "{info_plist}");
+        assert!(info_plist.contains("<string>macos-arm64_x86_64</string>"), "{info_plist}");
+        assert_eq!(info_plist.matches("<key>SupportedPlatformVariant</key>").count(), 1);
+        assert!(info_plist.contains("<key>CFBundlePackageType</key>\n\t<string>XFWK</string>"));
+    }
+
+    #[test]
+    fn rejects_duplicate_library_identifiers() {
+        let temp = tempfile::tempdir().unwrap();
+        let target_dir = Utf8Path::from_path(temp.path()).unwrap().join("target");
+        let generated_dir = temp.path().join("generated");
+
+        let targets = vec![target(ApplePlatform::IOS, false), target(ApplePlatform::IOS, false)];
+        fake_build(&targets, &target_dir, &generated_dir);
+
+        let result = create_xcframework(
+            &targets,
+            &target_dir,
+            "demo",
+            "Demo",
+            &generated_dir,
+            &temp.path().join("out"),
+            Mode::Release,
+            LibType::Static,
+        );
+        assert!(result.unwrap_err().to_string().contains("ios-arm64"));
     }
 }
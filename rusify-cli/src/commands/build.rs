@@ -0,0 +1,115 @@
+use anyhow::anyhow;
+
+use crate::android::package::build_android_package;
+use crate::apple::package::build_swift_package;
+use crate::common::metadata::Workspace;
+use crate::common::models::{Config, CrateSelection, FeatureArgs, Mode};
+use crate::common::platform::Platform;
+use crate::jvm::package::build_jvm_package;
+use crate::console::messages::{info, warning};
+use crate::ohos::package::build_ohos_package;
+use crate::wasm::package::build_wasm_package;
+use crate::Result;
+
+enum Outcome {
+    Built,
+    Skipped(String),
+    Failed(anyhow::Error),
+}
+
+/// Packages the selected crates for all given platforms in one run
+///
+/// Platforms whose toolchain is not available on the host are skipped. All other platforms are built
+/// even if one of them fails, and a summary is printed at the end.
+pub fn build_all(
+    workspace: &Workspace,
+    selection: &CrateSelection,
+    platforms: Option<Vec<Platform>>,
+    config: Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let platforms = platforms.unwrap_or_else(|| Platform::all().to_vec());
+    if platforms.is_empty() {
+        return Err(anyhow!("At least 1 platform needs to be selected!"));
+    }
+
+    let outcomes: Vec<_> = platforms
+        .iter()
+        .map(|platform| {
+            if let Err(reason) = platform.check_toolchain() {
+                return (*platform, Outcome::Skipped(reason));
+            }
+
+            info!(&config, "Packaging for {}", platform.display_name());
+            let result = build_platform(workspace, selection, *platform, &config, mode, features.clone());
+            match result {
+                Ok(()) => (*platform, Outcome::Built),
+                Err(e) => (*platform, Outcome::Failed(e)),
+            }
+        })
+        .collect();
+
+    info!(&config, "Summary:");
+    for (platform, outcome) in &outcomes {
+        match outcome {
+            Outcome::Built => info!(&config, "  {:<12} built", platform.display_name()),
+            Outcome::Skipped(reason) => {
+                warning!(&config, "  {:<12} skipped: {}", platform.display_name(), reason)
+            }
+            Outcome::Failed(_) => warning!(&config, "  {:<12} failed", platform.display_name()),
+        }
+    }
+
+    let built = outcomes
+        .iter()
+        .filter(|(_, outcome)| matches!(outcome, Outcome::Built))
+        .count();
+
+    let error = outcomes
+        .into_iter()
+        .filter_map(|(platform, outcome)| match outcome {
+            Outcome::Failed(e) => Some(e.context(format!("{} failed", platform.display_name()))),
+            _ => None,
+        })
+        .reduce(|acc, err| acc.context(err));
+
+    match error {
+        Some(error) => Err(error),
+        None if built == 0 => Err(anyhow!(
+            "None of the selected platforms could be built on this host!"
+        )),
+        None => Ok(()),
+    }
+}
+
+fn build_platform(
+    workspace: &Workspace,
+    selection: &CrateSelection,
+    platform: Platform,
+    config: &Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let config = config.clone();
+
+    match platform {
+        Platform::Apple => build_swift_package(
+            workspace, selection, None, None, None, None, vec![], None, None, None, None, None,
+            config, mode, None, features,
+        ),
+        Platform::Android => build_android_package(
+            workspace, selection, None, None, None, None, None, None, None, None, config, mode,
+            None, features,
+        ),
+        Platform::Jvm => {
+            build_jvm_package(workspace, selection, None, None, None, config, mode, features)
+        }
+        Platform::Ohos => build_ohos_package(
+            workspace, selection, None, None, None, None, config, mode, features,
+        ),
+        Platform::Wasm => {
+            build_wasm_package(workspace, selection, None, None, None, config, mode, features)
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use console::style;
+
+use crate::android::android_target::AndroidArch;
+use crate::android::ndk::{toolchain_dir, NdkSearch};
+use crate::apple::apple_target::ApplePlatform;
+use crate::common::platform::Platform;
+use crate::common::toolchain::{
+    has_nightly_with_rust_src, installed_targets, is_installed, tool_version,
+};
+use crate::jvm::jvm_target::JvmTarget;
+use crate::ohos::ohos_arch::{sdk_version, OhosArch};
+use crate::wasm::wasm_target::WASM_TARGET;
+use crate::Result;
+
+enum Status {
+    Ok,
+    /// Does not prevent building, e.g. experimental Apple platforms or Rust targets installed on
+    /// the first build
+    Warning,
+    Error,
+}
+
+struct Check {
+    name: String,
+    status: Status,
+    detail: String,
+}
+
+impl Check {
+    fn ok(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Ok,
+            detail: detail.into(),
+        }
+    }
+
+    fn warning(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Warning,
+            detail: detail.into(),
+        }
+    }
+
+    fn error(name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            status: Status::Error,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Checks the host environment for every given platform and prints a readiness report
+///
+/// In strict mode, an error is returned if any of the platforms is not ready to be built.
+pub fn doctor(platforms: Option<Vec<Platform>>, strict: bool) -> Result<()> {
+    let platforms = platforms.unwrap_or_else(|| Platform::all().to_vec());
+    let installed = installed_targets();
+
+    let reports: Vec<_> = platforms
+        .iter()
+        .map(|platform| {
+            let mut checks = match &installed {
+                Ok(_) => vec![],
+                Err(e) => vec![Check::error("Rust targets", e.to_string())],
+            };
+            let installed = installed.as_deref().unwrap_or_default();
+
+            checks.extend(match platform {
+                Platform::Apple => apple_checks(installed),
+                Platform::Android => android_checks(installed),
+                Platform::Jvm => jvm_checks(installed),
+                Platform::Ohos => ohos_checks(installed),
+                Platform::Wasm => wasm_checks(installed),
+            });
+            (*platform, checks)
+        })
+        .collect();
+
+    for (platform, checks) in &reports {
+        println!("{}", style(platform.display_name()).bold());
+        for check in checks {
+            let tag = match check.status {
+                Status::Ok => style("✔").green(),
+                Status::Warning => style("!").yellow(),
+                Status::Error => style("✘").red(),
+            };
+            println!("  {tag} {:<38} {}", check.name, check.detail);
+        }
+        println!();
+    }
+
+    println!("{}", style("Summary").bold());
+    let mut not_ready = 0;
+    for (platform, checks) in &reports {
+        let errors = count(checks, |s| matches!(s, Status::Error));
+        let warnings = count(checks, |s| matches!(s, Status::Warning));
+
+        let status = if errors > 0 {
+            not_ready += 1;
+            style(format!("not ready ({errors} problem(s))")).red()
+        } else if warnings > 0 {
+            style(format!("ready ({warnings} warning(s))")).yellow()
+        } else {
+            style("ready".to_string()).green()
+        };
+        println!("  {:<12} {status}", platform.display_name());
+    }
+
+    if strict && not_ready > 0 {
+        return Err(anyhow!(
+            "{not_ready} of {} platform(s) are not ready to be built!",
+            reports.len()
+        ));
+    }
+
+    Ok(())
+}
+
+fn count(checks: &[Check], predicate: impl Fn(&Status) -> bool) -> usize {
+    checks.iter().filter(|c| predicate(&c.status)).count()
+}
+
+/// Checks whether the standard library of a target is installed
+fn target_check(installed: &[String], target: &str) -> Check {
+    if installed.iter().any(|t| t == target) {
+        Check::ok(target, "installed")
+    } else {
+        missing_target_check(target, is_installed("rustup", "--version"))
+    }
+}
+
+/// Missing targets are only a warning if rustup can install them on the first build
+fn missing_target_check(target: &str, rustup: bool) -> Check {
+    if rustup {
+        Check::warning(
+            target,
+            format!("missing, will be installed on first build (or run `rustup target add {target}`)"),
+        )
+    } else {
+        Check::error(target, "missing, install the standard library of this target for your Rust toolchain")
+    }
+}
+
+fn env_dir_check(variable: &str, hint: &str) -> (Check, Option<PathBuf>) {
+    match std::env::var(variable) {
+        Ok(path) if PathBuf::from(&path).is_dir() => {
+            (Check::ok(variable, &path), Some(PathBuf::from(path)))
+        }
+        Ok(path) => (
+            Check::error(variable, format!("{path} does not exist")),
+            None,
+        ),
+        Err(_) => (Check::error(variable, format!("not set, {hint}")), None),
+    }
+}
+
+fn apple_checks(installed: &[String]) -> Vec<Check> {
+    let mut checks = vec![];
+
+    if cfg!(target_os = "macos") {
+        checks.push(Check::ok("Host", "macOS"));
+        // install_name_tool does not support a version flag
+        let tool = "install_name_tool";
+        if is_installed_via_xcrun(tool) {
+            checks.push(Check::ok(tool, "found"));
+        } else {
+            // Only dynamic libraries need their install name rewritten
+            checks.push(Check::warning(
+                tool,
+                "not found, dynamic libraries require the Xcode command line tools",
+            ));
+        }
+    } else {
+        checks.push(Check::error(
+            "Host",
+            "Apple platforms can only be packaged on macOS",
+        ));
+    }
+
+    let nightly = has_nightly_with_rust_src();
+    for platform in ApplePlatform::all() {
+        let targets = platform
+            .into_apple_platform_target()
+            .into_iter()
+            .map(|p| p.target(false));
+
+        if platform.is_tier_3() {
+            // The standard library of tier 3 targets is built from source with nightly
+            let name = format!("{} (nightly)", platform.display_name());
+            checks.push(if nightly {
+                Check::ok(name, "nightly with rust-src installed")
+            } else {
+                Check::warning(
+                    name,
+                    "missing, run `rustup toolchain install nightly --component rust-src`",
+                )
+            });
+            continue;
+        }
+
+        checks.extend(
+            targets
+                .flat_map(|t| t.architectures)
+                .map(|arch| target_check(installed, arch)),
+        );
+    }
+
+    checks
+}
+
+fn is_installed_via_xcrun(tool: &str) -> bool {
+    std::process::Command::new("xcrun")
+        .args(["--find", tool])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+fn android_checks(installed: &[String]) -> Vec<Check> {
+    let mut checks = vec![];
+
+    match NdkSearch::from_env().find(None) {
+        Ok(ndk) => {
+            checks.push(Check::ok(
+                "Android NDK",
+                format!("{} at {}", ndk.version, ndk.path.display()),
+            ));
+
+            for arch in AndroidArch::all().iter().flat_map(|a| a.target(21).architectures) {
+                let name = format!("NDK sysroot {arch}");
+                checks.push(match ndk.validate_api_level(arch, 21) {
+                    Ok(()) => {
+                        let levels = ndk.api_levels(arch);
+                        let (min, max) = (levels[0], levels[levels.len() - 1]);
+                        Check::ok(name, format!("API levels {min} to {max}"))
+                    }
+                    Err(e) => Check::error(name, e.to_string()),
+                });
+            }
+
+            let bin = toolchain_dir(&ndk.path).join("bin");
+            for tool in ["clang", "llvm-ar", "llvm-objcopy"] {
+                let exe_ext = if cfg!(target_os = "windows") { ".exe" } else { "" };
+                let path = bin.join(format!("{tool}{exe_ext}"));
+                checks.push(if path.exists() {
+                    Check::ok(format!("NDK {tool}"), path.display().to_string())
+                } else {
+                    Check::error(format!("NDK {tool}"), format!("{} not found", path.display()))
+                });
+            }
+        }
+        Err(e) => checks.push(Check::error("Android NDK", e.to_string())),
+    }
+
+    checks.extend(
+        AndroidArch::all()
+            .iter()
+            .flat_map(|arch| arch.target(21).architectures)
+            .map(|arch| target_check(installed, arch)),
+    );
+
+    // Without kotlinc, AARs only contain the native libraries and the bindings as sources
+    checks.push(match tool_version("kotlinc", "-version") {
+        Some(version) => Check::ok("kotlinc", version),
+        None => Check::warning(
+            "kotlinc",
+            "not found, AARs will not contain the compiled Kotlin bindings",
+        ),
+    });
+
+    checks
+}
+
+fn jvm_checks(installed: &[String]) -> Vec<Check> {
+    let mut checks = vec![];
+
+    let host = JvmTarget::host();
+    match host {
+        Some(host) => {
+            checks.push(Check::ok("Host", host.display_name()));
+            checks.push(target_check(installed, host.triple()));
+        }
+        None => checks.push(Check::error(
+            "Host",
+            "not a supported desktop platform, all targets require cross-compilation",
+        )),
+    }
+
+    // Other platforms are optional and need a cross-compilation toolchain besides the Rust target
+    for target in JvmTarget::all().into_iter().filter(|target| Some(*target) != host) {
+        let triple = target.triple();
+        checks.push(if installed.iter().any(|t| t == triple) {
+            Check::ok(triple, "installed")
+        } else {
+            Check::warning(triple, format!("missing, run `rustup target add {triple}` to bundle it"))
+        });
+    }
+
+    checks.push(match tool_version("kotlinc", "-version") {
+        Some(version) => Check::ok("kotlinc", version),
+        None => Check::warning(
+            "kotlinc",
+            "not found, JARs will not contain the compiled Kotlin bindings",
+        ),
+    });
+
+    checks
+}
+
+fn ohos_checks(installed: &[String]) -> Vec<Check> {
+    let (sdk_check, ndk_home) = env_dir_check(
+        "OHOS_NDK_HOME",
+        "please install the OpenHarmony SDK and set this variable",
+    );
+    let mut checks = vec![sdk_check];
+
+    if let Some(ndk_home) = ndk_home {
+        checks.push(match sdk_version(&ndk_home) {
+            Some(version) => Check::ok("SDK version", version),
+            None => Check::warning("SDK version", "native/oh-uni-package.json not found"),
+        });
+
+        let exe_ext = if cfg!(target_os = "windows") { ".exe" } else { "" };
+        let bin = ndk_home.join("native").join("llvm").join("bin");
+        for tool in ["clang", "llvm-ar"] {
+            let path = bin.join(format!("{tool}{exe_ext}"));
+            checks.push(if path.exists() {
+                Check::ok(format!("SDK {tool}"), path.display().to_string())
+            } else {
+                Check::error(format!("SDK {tool}"), format!("{} not found", path.display()))
+            });
+        }
+    }
+
+    checks.extend(
+        OhosArch::all()
+            .iter()
+            .map(|arch| target_check(installed, arch.rust_target())),
+    );
+
+    checks
+}
+
+fn wasm_checks(installed: &[String]) -> Vec<Check> {
+    vec![target_check(installed, WASM_TARGET)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn installed_targets_are_ok() {
+        let installed = vec!["aarch64-linux-android".to_string()];
+
+        let check = target_check(&installed, "aarch64-linux-android");
+        assert!(matches!(check.status, Status::Ok));
+    }
+
+    #[test]
+    fn missing_targets_are_warnings_with_rustup() {
+        let check = missing_target_check("x86_64-linux-android", true);
+        assert!(matches!(check.status, Status::Warning));
+        assert!(check.detail.contains("rustup target add x86_64-linux-android"), "{}", check.detail);
+
+        let check = missing_target_check("x86_64-linux-android", false);
+        assert!(matches!(check.status, Status::Error));
+    }
+}
@@ -1,32 +1,72 @@
 use std::borrow::Cow;
 
+use crate::common::models::CrateSelection;
 use crate::path::PathExt;
-use camino::Utf8Path;
-use cargo_metadata::{Metadata, MetadataCommand, Package};
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::{Metadata, MetadataCommand, Package, Target};
+use anyhow::{anyhow, Context};
 use itertools::Itertools;
-use lazy_static::lazy_static;
 
-pub(crate) fn metadata() -> &'static Metadata {
-    lazy_static! {
-        static ref METADATA: Metadata = MetadataCommand::new()
-            .no_deps()
-            .other_options(["--offline".to_string()])
-            .exec()
-            // TODO: Error handling
-            .unwrap();
-    }
+/// Crates depending on any of these are considered to expose an FFI
+const FFI_DEPENDENCIES: [&str; 3] = ["uniffi", "rusify", "rusify-macro"];
+
+/// Options controlling how the cargo metadata of the workspace is resolved
+#[derive(Debug, Clone)]
+pub struct MetadataOptions {
+    /// Cargo.toml of the crate or workspace, the current working directory is searched if unset
+    pub manifest_path: Option<Utf8PathBuf>,
+    /// Whether cargo may access the network while reading the workspace
+    pub offline: bool,
+}
 
-    &METADATA
+impl Default for MetadataOptions {
+    fn default() -> Self {
+        Self {
+            manifest_path: None,
+            offline: true,
+        }
+    }
 }
 
-pub(crate) trait MetadataExt {
-    fn target_dir(&self) -> Cow<Utf8Path>;
-    fn current_crate(&self) -> Option<&Package>;
+/// The cargo workspace being packaged
+///
+/// The metadata is loaded once per invocation and passed to the build pipelines.
+#[derive(Debug)]
+pub struct Workspace {
+    pub metadata: Metadata,
+    manifest_path: Option<Utf8PathBuf>,
 }
 
-impl MetadataExt for Metadata {
-    fn target_dir(&self) -> Cow<Utf8Path> {
-        let target_dir = self.target_directory.as_path();
+impl Workspace {
+    pub fn load(options: &MetadataOptions) -> crate::Result<Self> {
+        if let Some(manifest_path) = &options.manifest_path {
+            if !manifest_path.is_file() {
+                return Err(anyhow!(
+                    "Manifest {manifest_path} does not exist! --manifest-path must point to a Cargo.toml file."
+                ));
+            }
+        }
+
+        let mut command = MetadataCommand::new();
+        command.no_deps();
+        if let Some(manifest_path) = &options.manifest_path {
+            command.manifest_path(manifest_path);
+        }
+        if options.offline {
+            command.other_options(["--offline".to_string()]);
+        }
+
+        let metadata = command.exec().map_err(|e| metadata_error(e, options))?;
+
+        Ok(Self {
+            metadata,
+            manifest_path: options.manifest_path.clone(),
+        })
+    }
+
+    /// Returns the target directory, relative to the current working directory if possible
+    pub fn target_dir(&self) -> Cow<'_, Utf8Path> {
+        let target_dir = self.metadata.target_directory.as_path();
         let relative = target_dir.to_relative();
 
         match relative {
@@ -36,10 +76,16 @@ impl MetadataExt for Metadata {
     }
 
     /// Returns the package metadata for the crate currently at or above the current working directory.
-    fn current_crate(&self) -> Option<&Package> {
-        let cwd = std::env::current_dir().unwrap();
+    ///
+    /// If a manifest path was given, the directory containing it is used instead of the working directory.
+    pub fn current_crate(&self) -> crate::Result<&Package> {
+        let cwd = match self.manifest_path.as_ref().and_then(|path| path.parent()) {
+            Some(dir) if !dir.as_str().is_empty() => std::path::absolute(dir)?,
+            _ => std::env::current_dir()?,
+        };
 
-        self.workspace_packages()
+        self.metadata
+            .workspace_packages()
             .into_iter()
             .filter_map(|p| {
                 let parent = p
@@ -55,5 +101,256 @@ impl MetadataExt for Metadata {
             })
             .find_or_first(|(_, parent)| parent.starts_with(&cwd))
             .map(|(package, _)| package)
+            .with_context(|| {
+                format!(
+                    "No crate found at {}! Run rusify inside a crate, pass --manifest-path, or select crates with --package or --workspace.",
+                    cwd.display()
+                )
+            })
+    }
+
+    /// Returns all workspace members exposing an FFI through UniFFI or the rusify macros
+    pub fn uniffi_crates(&self) -> Vec<&Package> {
+        self.metadata
+            .workspace_packages()
+            .into_iter()
+            .filter(|p| exposes_ffi(p))
+            .filter(|p| p.targets.iter().any(is_library))
+            .collect()
+    }
+
+    /// Returns the crates that should be packaged
+    ///
+    /// Explicitly named packages take precedence, followed by all FFI crates of the workspace if
+    /// `--workspace` was passed. Otherwise, the crate at the current working directory is selected.
+    pub fn selected_crates(&self, selection: &CrateSelection) -> crate::Result<Vec<&Package>> {
+        if !selection.packages.is_empty() {
+            let members = self.metadata.workspace_packages();
+            return selection
+                .packages
+                .iter()
+                .map(|name| {
+                    members
+                        .iter()
+                        .find(|p| p.name.as_str() == name)
+                        .copied()
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Package {name} is not a member of the workspace! Available members: {}",
+                                members.iter().map(|p| p.name.as_str()).join(", ")
+                            )
+                        })
+                        .and_then(|p| {
+                            if exposes_ffi(p) {
+                                Ok(p)
+                            } else {
+                                Err(anyhow!(
+                                    "Package {name} does not depend on uniffi or the rusify macros, so there are no bindings to package!"
+                                ))
+                            }
+                        })
+                })
+                .collect();
+        }
+
+        if selection.workspace {
+            let crates = self.uniffi_crates();
+            if crates.is_empty() {
+                return Err(anyhow!(
+                    "No crate in the workspace depends on uniffi or the rusify macros!"
+                ));
+            }
+            return Ok(crates);
+        }
+
+        Ok(vec![self.current_crate()?])
+    }
+}
+
+/// Returns whether the given crate exposes an FFI through UniFFI or the rusify macros
+fn exposes_ffi(package: &Package) -> bool {
+    package
+        .dependencies
+        .iter()
+        .any(|d| FFI_DEPENDENCIES.contains(&d.name.as_str()))
+}
+
+/// Returns whether the given target is a library of any crate type
+///
+/// Cargo only reports the `lib` kind if the crate type is not set, FFI crates usually set it to
+/// `staticlib` and `cdylib`.
+fn is_library(target: &Target) -> bool {
+    target.is_lib()
+        || target.is_staticlib()
+        || target.is_cdylib()
+        || target.is_dylib()
+        || target.is_rlib()
+}
+
+/// Returns the library target of the given crate
+pub(crate) fn library_target(package: &Package) -> crate::Result<&Target> {
+    package.targets.iter().find(|t| is_library(t)).with_context(|| {
+        format!(
+            "Crate {} has no library target! Add a [lib] section with crate-type = [\"staticlib\", \"cdylib\"] to {}.",
+            package.name, package.manifest_path
+        )
+    })
+}
+
+fn metadata_error(error: cargo_metadata::Error, options: &MetadataOptions) -> anyhow::Error {
+    let location = match &options.manifest_path {
+        Some(manifest_path) => manifest_path.to_string(),
+        None => "the current directory".to_string(),
+    };
+
+    match error {
+        cargo_metadata::Error::CargoMetadata { stderr } => {
+            let hint = if options.offline && stderr.contains("offline") {
+                "\nRun again with --online to allow cargo to fetch missing dependencies."
+            } else if stderr.contains("could not find `Cargo.toml`") {
+                "\nRun rusify inside a crate or pass --manifest-path."
+            } else {
+                ""
+            };
+            anyhow!(
+                "Could not read the cargo workspace at {location}: \n{}{hint}",
+                stderr.trim_end()
+            )
+        }
+        cargo_metadata::Error::Io(e) => {
+            anyhow!("Could not run cargo to read the workspace at {location}: {e}. Is cargo installed?")
+        }
+        error => anyhow!("Could not read the cargo workspace at {location}: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::{json, Value};
+
+    fn dependency(name: &str) -> Value {
+        json!({
+            "name": name,
+            "source": "registry+https://github.com/rust-lang/crates.io-index",
+            "req": "*",
+            "kind": null,
+            "optional": false,
+            "uses_default_features": true,
+            "features": [],
+            "target": null,
+            "rename": null,
+            "registry": null,
+            "path": null,
+        })
+    }
+
+    fn package(name: &str, dependencies: &[&str], kinds: &[&str]) -> Value {
+        json!({
+            "name": name,
+            "version": "0.1.0",
+            "id": format!("path+file:///workspace/{name}#0.1.0"),
+            "dependencies": dependencies.iter().map(|d| dependency(d)).collect::<Vec<_>>(),
+            "targets": [{
+                "name": name,
+                "kind": kinds,
+                "crate_types": kinds,
+                "src_path": format!("/workspace/{name}/src/lib.rs"),
+                "edition": "2021",
+            }],
+            "features": {},
+            "manifest_path": format!("/workspace/{name}/Cargo.toml"),
+        })
+    }
+
+    /// A workspace with a UniFFI crate, a crate using the rusify macros and two crates without an FFI
+    fn workspace() -> Workspace {
+        let packages = [
+            package("math", &["uniffi", "serde"], &["lib"]),
+            package("macros", &["rusify"], &["staticlib", "cdylib"]),
+            package("util", &["serde"], &["lib"]),
+            package("tool", &["uniffi"], &["bin"]),
+        ];
+        let metadata = serde_json::from_value(json!({
+            "packages": packages,
+            "workspace_members": packages.iter().map(|p| p["id"].clone()).collect::<Vec<_>>(),
+            "resolve": null,
+            "target_directory": "/workspace/target",
+            "version": 1,
+            "workspace_root": "/workspace",
+        }))
+        .unwrap();
+
+        Workspace {
+            metadata,
+            manifest_path: None,
+        }
+    }
+
+    fn names(crates: Vec<&Package>) -> Vec<&str> {
+        crates.into_iter().map(|p| p.name.as_str()).collect()
+    }
+
+    fn packages(names: &[&str]) -> CrateSelection {
+        CrateSelection {
+            packages: names.iter().map(|name| name.to_string()).collect(),
+            workspace: false,
+        }
+    }
+
+    #[test]
+    fn libraries_depending_on_uniffi_or_rusify_are_discovered() {
+        let workspace = workspace();
+        assert_eq!(names(workspace.uniffi_crates()), vec!["math", "macros"]);
+
+        let selection = CrateSelection {
+            packages: vec![],
+            workspace: true,
+        };
+        assert_eq!(names(workspace.selected_crates(&selection).unwrap()), vec!["math", "macros"]);
+    }
+
+    #[test]
+    fn named_packages_take_precedence() {
+        let workspace = workspace();
+        let mut selection = packages(&["macros"]);
+        selection.workspace = true;
+
+        assert_eq!(names(workspace.selected_crates(&selection).unwrap()), vec!["macros"]);
+    }
+
+    #[test]
+    fn library_targets_of_any_crate_type_are_found() {
+        let workspace = workspace();
+        let packages = workspace.metadata.workspace_packages();
+
+        assert_eq!(library_target(packages[1]).unwrap().name, "macros");
+        let error = library_target(packages[3]).unwrap_err().to_string();
+        assert!(error.contains("Crate tool has no library target"), "{error}");
+    }
+
+    #[test]
+    fn unknown_packages_are_rejected() {
+        let error = workspace()
+            .selected_crates(&packages(&["math", "missing"]))
+            .unwrap_err()
+            .to_string();
+
+        assert!(error.contains("Package missing is not a member of the workspace"), "{error}");
+        assert!(error.contains("math, macros, util, tool"), "{error}");
+    }
+
+    #[test]
+    fn packages_without_ffi_are_rejected() {
+        let error = workspace()
+            .selected_crates(&packages(&["util"]))
+            .unwrap_err()
+            .to_string();
+
+        assert!(
+            error.contains("Package util does not depend on uniffi or the rusify macros"),
+            "{error}"
+        );
     }
 }
@@ -1,5 +1,9 @@
 use std::{fmt::Display, str::FromStr};
 
+use camino::Utf8PathBuf;
+use cargo_metadata::Package;
+use convert_case::{Case, Casing};
+
 #[derive(clap::ValueEnum, Debug, PartialEq, Eq, Clone, Copy)]
 #[value()]
 pub enum LibType {
@@ -13,9 +17,24 @@ pub enum Mode {
     Release,
 }
 
+#[derive(Debug, Clone)]
 pub struct Config {
     pub silent: bool,
     pub accept_all: bool,
+    /// Directory all packages and generated bindings are written to
+    pub out_dir: Utf8PathBuf,
+}
+
+impl Config {
+    /// Directory the UniFFI bindings and other intermediate sources are generated into
+    pub fn generated_dir(&self) -> Utf8PathBuf {
+        self.out_dir.join("generated")
+    }
+
+    /// Location of an output artifact with the given name
+    pub fn output_path(&self, name: &str) -> Utf8PathBuf {
+        self.out_dir.join(name)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -25,6 +44,36 @@ pub struct FeatureOptions {
     pub no_default_features: bool,
 }
 
+/// Feature selection passed on the command line
+///
+/// Flags are `None` unless they or their negation were passed, so that the project configuration
+/// applies. Resolved into [`FeatureOptions`] by `ProjectConfig::features`.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureArgs {
+    pub features: Option<Vec<String>>,
+    pub all_features: Option<bool>,
+    pub no_default_features: Option<bool>,
+}
+
+/// Selects the crates of a workspace that should be packaged
+#[derive(Debug, Clone, Default)]
+pub struct CrateSelection {
+    pub packages: Vec<String>,
+    pub workspace: bool,
+}
+
+/// Returns the name of an output artifact
+///
+/// When several crates are packaged in one run, the crate name is used instead of the default so
+/// that the outputs do not overwrite each other.
+pub(crate) fn default_output_name(default: &str, package: &Package, multiple_crates: bool) -> String {
+    if multiple_crates {
+        package.name.to_case(Case::UpperCamel)
+    } else {
+        default.to_string()
+    }
+}
+
 impl Display for Mode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,6 +1,7 @@
 use crate::Result;
 use camino::{Utf8Path, Utf8PathBuf};
-use std::fs::{create_dir, remove_dir_all};
+use anyhow::Context;
+use std::fs::{self, create_dir_all, remove_dir_all};
 use std::io;
 use std::ops::Deref;
 use std::path::Path;
@@ -11,10 +12,28 @@ where
 {
     match remove_dir_all(&dir) {
         Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
-        _ => create_dir(&dir).map_err(|e| e.into()),
+        _ => create_dir_all(&dir).map_err(|e| e.into()),
     }
 }
 
+/// Recursively copies the contents of `source` into `destination`
+pub(crate) fn copy_dir(source: &Path, destination: &Path) -> Result<()> {
+    create_dir_all(destination)?;
+
+    for entry in fs::read_dir(source)? {
+        let path = entry?.path();
+        let file_name = path.file_name().context("Directory entry has no file name")?;
+
+        if path.is_dir() {
+            copy_dir(&path, &destination.join(file_name))?;
+        } else {
+            fs::copy(&path, destination.join(file_name))?;
+        }
+    }
+
+    Ok(())
+}
+
 pub(crate) trait PathExt {
     fn to_relative(&self) -> Result<Utf8PathBuf>;
     fn find_common_path(&self, other: &Utf8Path) -> Utf8PathBuf;
@@ -0,0 +1,61 @@
+use crate::android::ndk::NdkSearch;
+use crate::jvm::jvm_target::JvmTarget;
+
+/// The platforms a crate can be packaged for
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Platform {
+    /// Swift package with an XCFramework
+    Apple,
+    /// Android AAR
+    Android,
+    /// JAR for desktop JVM applications
+    Jvm,
+    /// HarmonyOS HAR
+    Ohos,
+    /// npm package with WebAssembly
+    Wasm,
+}
+
+impl Platform {
+    pub(crate) fn display_name(&self) -> &'static str {
+        match self {
+            Platform::Apple => "Apple",
+            Platform::Android => "Android",
+            Platform::Jvm => "JVM",
+            Platform::Ohos => "HarmonyOS",
+            Platform::Wasm => "WebAssembly",
+        }
+    }
+
+    pub(crate) fn all() -> [Self; 5] {
+        [Self::Apple, Self::Android, Self::Jvm, Self::Ohos, Self::Wasm]
+    }
+
+    /// Checks whether the toolchain required for this platform is available on the host
+    ///
+    /// Returns the reason why the platform cannot be built otherwise.
+    pub(crate) fn check_toolchain(&self) -> Result<(), String> {
+        match self {
+            // XCFrameworks and universal libraries are assembled natively, so the Command Line Tools suffice
+            Platform::Apple => {
+                if cfg!(target_os = "macos") {
+                    Ok(())
+                } else {
+                    Err("Apple platforms can only be packaged on macOS".to_string())
+                }
+            }
+            Platform::Android => NdkSearch::from_env()
+                .find(None)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            Platform::Jvm => JvmTarget::host()
+                .map(|_| ())
+                .ok_or_else(|| "The host is not a supported desktop platform".to_string()),
+            Platform::Ohos => std::env::var("OHOS_NDK_HOME")
+                .map(|_| ())
+                .map_err(|_| "OHOS_NDK_HOME is not set".to_string()),
+            // The bindings are generated in-process, only the Rust target is required
+            Platform::Wasm => Ok(()),
+        }
+    }
+}
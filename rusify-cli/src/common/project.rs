@@ -0,0 +1,396 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use cargo_metadata::Package;
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer};
+
+use crate::android::android_target::{AndroidArch, AndroidOutput};
+use crate::apple::apple_target::ApplePlatform;
+use crate::apple::distribution::Distribution;
+use crate::common::models::{Config, FeatureArgs, FeatureOptions, LibType, Mode};
+use crate::jvm::jvm_target::JvmTarget;
+use crate::ohos::ohos_arch::OhosArch;
+use crate::wasm::wasm_target::WasmFlavor;
+use crate::console::messages::warning;
+use crate::Result;
+
+pub const PROJECT_CONFIG_FILE: &str = "rusify.toml";
+
+/// Per-crate configuration read from `rusify.toml` or `[package.metadata.rusify]` in Cargo.toml
+///
+/// Every value is optional. Values passed on the command line always take precedence over the ones
+/// configured here. Enum values use the same spelling as the corresponding command line arguments.
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProjectConfig {
+    pub release: bool,
+    pub features: Option<Vec<String>>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub apple: AppleConfig,
+    pub android: AndroidConfig,
+    pub jvm: JvmConfig,
+    pub ohos: OhosConfig,
+    pub wasm: WasmConfig,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AppleConfig {
+    pub package_name: Option<String>,
+    pub xcframework_name: Option<String>,
+    #[serde(deserialize_with = "value_enums")]
+    pub platforms: Option<Vec<ApplePlatform>>,
+    #[serde(deserialize_with = "value_enum")]
+    pub lib_type: Option<LibType>,
+    pub suppress_warnings: bool,
+    /// Builds macOS and the iOS Simulator for arm64 only instead of universal libraries with x86_64
+    pub apple_silicon_only: bool,
+    /// Minimum versions declared in Package.swift by platform, e.g. `ios = "15.0"`
+    #[serde(deserialize_with = "value_enum_map")]
+    pub deployment_targets: Option<Vec<(ApplePlatform, String)>>,
+    pub swift_tools_version: Option<String>,
+    #[serde(deserialize_with = "value_enum")]
+    pub distribution: Option<Distribution>,
+    /// URL the zipped XCFramework is uploaded to for remote distribution, without the file name
+    pub url_base: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AndroidConfig {
+    pub package_name: Option<String>,
+    pub aar_name: Option<String>,
+    pub api_level: Option<u32>,
+    pub ndk_version: Option<String>,
+    /// Jars the Kotlin bindings are compiled against, relative to the crate directory
+    pub kotlin_classpath: Option<Vec<PathBuf>>,
+    /// File with ProGuard/R8 rules appended to the generated consumer rules, relative to the crate directory
+    pub consumer_proguard_rules: Option<PathBuf>,
+    /// Permissions declared in the manifest, e.g. `INTERNET` or `android.permission.INTERNET`
+    pub permissions: Option<Vec<String>>,
+    /// Value of `android:extractNativeLibs`, left to the app if not set
+    pub extract_native_libs: Option<bool>,
+    #[serde(deserialize_with = "value_enums")]
+    pub archs: Option<Vec<AndroidArch>>,
+    #[serde(deserialize_with = "value_enum")]
+    pub lib_type: Option<LibType>,
+    #[serde(deserialize_with = "value_enum")]
+    pub output: Option<AndroidOutput>,
+    /// Maven repository directory the AAR is published to, relative to the crate directory
+    pub maven_repo: Option<PathBuf>,
+    pub maven_group_id: Option<String>,
+    pub maven_artifact_id: Option<String>,
+    pub maven_version: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct JvmConfig {
+    pub package_name: Option<String>,
+    pub jar_name: Option<String>,
+    /// Desktop platforms bundled into the JAR and the jvmMain source set of Kotlin Multiplatform modules
+    #[serde(deserialize_with = "value_enums")]
+    pub targets: Option<Vec<JvmTarget>>,
+    /// Jars the Kotlin bindings are compiled against, relative to the crate directory
+    pub kotlin_classpath: Option<Vec<PathBuf>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OhosConfig {
+    pub package_name: Option<String>,
+    pub har_name: Option<String>,
+    #[serde(deserialize_with = "value_enums")]
+    pub archs: Option<Vec<OhosArch>>,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WasmConfig {
+    pub package_name: Option<String>,
+    pub pkg_dir: Option<String>,
+    #[serde(deserialize_with = "value_enum")]
+    pub flavor: Option<WasmFlavor>,
+}
+
+impl ProjectConfig {
+    /// Loads the configuration of the given crate
+    ///
+    /// A `rusify.toml` next to the crate's Cargo.toml takes precedence over `[package.metadata.rusify]`,
+    /// which is ignored with a warning if both exist. If neither exists, the default (empty)
+    /// configuration is returned.
+    pub fn load(package: &Package, config: &Config) -> Result<Self> {
+        let crate_dir = package
+            .manifest_path
+            .parent()
+            .expect("The Cargo.toml path should end with /Cargo.toml");
+
+        let config_file = crate_dir.join(PROJECT_CONFIG_FILE);
+        if config_file.exists() {
+            if package.metadata.get("rusify").is_some() {
+                warning!(
+                    config,
+                    "Both {config_file} and [package.metadata.rusify] in {} configure {}, using {config_file}",
+                    package.manifest_path,
+                    package.name
+                );
+            }
+
+            let content = fs::read_to_string(&config_file)
+                .with_context(|| format!("Could not read {config_file}"))?;
+            return toml::from_str(&content)
+                .map_err(|e| anyhow!("Invalid configuration in {config_file}: \n{e}"));
+        }
+
+        match package.metadata.get("rusify") {
+            Some(metadata) => serde_json::from_value(metadata.clone()).map_err(|e| {
+                anyhow!(
+                    "Invalid configuration in [package.metadata.rusify] of {}: \n{e}",
+                    package.manifest_path
+                )
+            }),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Returns the build mode, preferring the one passed on the command line over the configured one
+    pub fn mode(&self, mode: Option<Mode>) -> Mode {
+        mode.unwrap_or(if self.release {
+            Mode::Release
+        } else {
+            Mode::Debug
+        })
+    }
+
+    /// Returns the feature selection, preferring the flags passed on the command line over the
+    /// configured ones
+    pub fn features(&self, features: FeatureArgs) -> FeatureOptions {
+        FeatureOptions {
+            features: features.features.or_else(|| self.features.clone()),
+            all_features: features.all_features.unwrap_or(self.all_features),
+            no_default_features: features
+                .no_default_features
+                .unwrap_or(self.no_default_features),
+        }
+    }
+}
+
+fn value_enum<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|value| T::from_str(&value, true).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+fn value_enums<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|values| {
+            values
+                .iter()
+                .map(|value| T::from_str(value, true).map_err(serde::de::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
+
+fn value_enum_map<'de, D, T>(deserializer: D) -> Result<Option<Vec<(T, String)>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: ValueEnum,
+{
+    Option::<BTreeMap<String, String>>::deserialize(deserializer)?
+        .map(|values| {
+            values
+                .into_iter()
+                .map(|(key, value)| {
+                    T::from_str(&key, true)
+                        .map(|key| (key, value))
+                        .map_err(serde::de::Error::custom)
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use camino::Utf8PathBuf;
+
+    fn configured() -> ProjectConfig {
+        ProjectConfig {
+            release: true,
+            features: Some(vec!["configured".to_string()]),
+            all_features: true,
+            no_default_features: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn configuration_applies_without_command_line_flags() {
+        let project = configured();
+        assert!(matches!(project.mode(None), Mode::Release));
+
+        let features = project.features(FeatureArgs::default());
+        assert_eq!(features.features, Some(vec!["configured".to_string()]));
+        assert!(features.all_features);
+        assert!(features.no_default_features);
+    }
+
+    #[test]
+    fn command_line_flags_override_configuration() {
+        let project = configured();
+        assert!(matches!(project.mode(Some(Mode::Debug)), Mode::Debug));
+
+        let features = project.features(FeatureArgs {
+            features: Some(vec![]),
+            all_features: Some(false),
+            no_default_features: Some(false),
+        });
+        assert_eq!(features.features, Some(vec![]));
+        assert!(!features.all_features);
+        assert!(!features.no_default_features);
+    }
+
+    /// Returns a package at `dir` with the given `[package.metadata]`
+    fn package(dir: &std::path::Path, metadata: serde_json::Value) -> Package {
+        serde_json::from_value(serde_json::json!({
+            "name": "demo",
+            "version": "0.1.0",
+            "id": "path+file:///demo#0.1.0",
+            "dependencies": [],
+            "targets": [],
+            "features": {},
+            "manifest_path": dir.join("Cargo.toml"),
+            "metadata": metadata,
+        }))
+        .unwrap()
+    }
+
+    fn silent() -> Config {
+        Config {
+            silent: true,
+            accept_all: true,
+            out_dir: Utf8PathBuf::from("out"),
+        }
+    }
+
+    #[test]
+    fn enum_values_are_parsed_like_command_line_arguments() {
+        let project: ProjectConfig = toml::from_str(
+            r#"
+            release = true
+
+            [apple]
+            platforms = ["ios", "MAC-OS"]
+            lib-type = "dynamic"
+            distribution = "remote"
+            deployment-targets = { ios = "15.0", mac-os = "12.0" }
+
+            [android]
+            archs = ["arm64v8a", "x86"]
+            "#,
+        )
+        .unwrap();
+
+        assert!(project.release);
+        assert_eq!(project.apple.platforms, Some(vec![ApplePlatform::IOS, ApplePlatform::MacOS]));
+        assert_eq!(project.apple.lib_type, Some(LibType::Dynamic));
+        assert_eq!(project.apple.distribution, Some(Distribution::Remote));
+        assert_eq!(
+            project.apple.deployment_targets,
+            Some(vec![
+                (ApplePlatform::IOS, "15.0".to_string()),
+                (ApplePlatform::MacOS, "12.0".to_string()),
+            ])
+        );
+        assert!(matches!(
+            project.android.archs.as_deref(),
+            Some([AndroidArch::ARM64V8A, AndroidArch::X86])
+        ));
+        assert_eq!(project.android.lib_type, None);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let unknown_key = toml::from_str::<ProjectConfig>("[apple]\nsuppress-warning = true\n");
+        let error = unknown_key.unwrap_err().to_string();
+        assert!(error.contains("unknown field `suppress-warning`"), "{error}");
+
+        let unknown_section = toml::from_str::<ProjectConfig>("[ios]\n");
+        assert!(unknown_section.is_err());
+
+        let invalid_enum = toml::from_str::<ProjectConfig>("[apple]\nplatforms = [\"android\"]\n");
+        let error = invalid_enum.unwrap_err().to_string();
+        assert!(error.contains("android"), "{error}");
+
+        let invalid_map =
+            toml::from_str::<ProjectConfig>("[apple.deployment-targets]\nwindows = \"10\"\n");
+        assert!(invalid_map.is_err());
+    }
+
+    #[test]
+    fn cargo_metadata_is_used_without_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = package(
+            dir.path(),
+            serde_json::json!({ "rusify": { "release": true, "apple": { "xcframework-name": "Metadata" } } }),
+        );
+
+        let project = ProjectConfig::load(&package, &silent()).unwrap();
+        assert!(project.release);
+        assert_eq!(project.apple.xcframework_name.as_deref(), Some("Metadata"));
+    }
+
+    #[test]
+    fn config_file_takes_precedence_over_cargo_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(PROJECT_CONFIG_FILE),
+            "[apple]\nxcframework-name = \"File\"\n",
+        )
+        .unwrap();
+        let package = package(
+            dir.path(),
+            serde_json::json!({ "rusify": { "release": true, "apple": { "xcframework-name": "Metadata" } } }),
+        );
+
+        let project = ProjectConfig::load(&package, &silent()).unwrap();
+        assert!(!project.release);
+        assert_eq!(project.apple.xcframework_name.as_deref(), Some("File"));
+    }
+
+    #[test]
+    fn invalid_cargo_metadata_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = package(dir.path(), serde_json::json!({ "rusify": { "relase": true } }));
+
+        let error = ProjectConfig::load(&package, &silent()).unwrap_err().to_string();
+        assert!(error.contains("[package.metadata.rusify]"), "{error}");
+        assert!(error.contains("relase"), "{error}");
+    }
+
+    #[test]
+    fn defaults_apply_without_configuration() {
+        let project = ProjectConfig::default();
+        assert!(matches!(project.mode(None), Mode::Debug));
+
+        let features = project.features(FeatureArgs::default());
+        assert_eq!(features.features, None);
+        assert!(!features.all_features);
+        assert!(!features.no_default_features);
+    }
+}
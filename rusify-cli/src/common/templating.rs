@@ -13,19 +13,160 @@ pub(crate) struct LibRs;
 #[derive(Template)]
 #[template(path = "Package.swift.txt", escape = "none")]
 pub(crate) struct PackageSwift<'a> {
+    pub(crate) swift_tools_version: &'a str,
     pub(crate) package_name: &'a str,
+    pub(crate) platforms: &'a [SwiftPlatform],
     pub(crate) xcframework_name: &'a str,
+    /// Set if the XCFramework is hosted remotely instead of being part of the package
+    pub(crate) remote_binary: Option<&'a RemoteBinaryTarget>,
     pub(crate) disable_warnings: bool,
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct RemoteBinaryTarget {
+    pub(crate) url: String,
+    /// SHA-256 of the zipped XCFramework
+    pub(crate) checksum: String,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SwiftPlatform {
+    /// Name of the `SupportedPlatform`, e.g. `iOS`
+    pub(crate) name: &'static str,
+    /// Minimum deployment target, e.g. `13.0`
+    pub(crate) version: String,
+}
+
+#[derive(Template)]
+#[template(path = "xcframework-Info.plist.txt", escape = "html")]
+pub(crate) struct XcframeworkInfoPlist<'a> {
+    pub(crate) libraries: &'a [XcframeworkLibrary],
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct XcframeworkLibrary {
+    /// Directory of the library in the XCFramework, e.g. `ios-arm64_x86_64-simulator`
+    pub(crate) identifier: String,
+    /// File name of the library in its directory
+    pub(crate) library_path: String,
+    pub(crate) headers_path: &'static str,
+    pub(crate) architectures: Vec<&'static str>,
+    pub(crate) platform: &'static str,
+    pub(crate) platform_variant: Option<&'static str>,
+}
+
 #[derive(Template)]
 #[template(path = "AndroidManifest.xml.txt", escape = "none")]
 pub(crate) struct AndroidManifest<'a> {
+    /// Only set in AARs, Gradle modules declare the namespace in build.gradle.kts instead
+    pub(crate) package_name: Option<&'a str>,
+    pub(crate) min_sdk: Option<u32>,
+    pub(crate) permissions: &'a [String],
+    pub(crate) extract_native_libs: Option<bool>,
+}
+
+#[derive(Template)]
+#[template(path = "build.gradle.kts.txt", escape = "none")]
+pub(crate) struct GradleBuildScript<'a> {
+    pub(crate) namespace: &'a str,
+    pub(crate) min_sdk: u32,
+    pub(crate) compile_sdk: u32,
+    pub(crate) consumer_rules: &'a str,
+    pub(crate) jna_version: &'a str,
+    pub(crate) coroutines_version: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "kmp.build.gradle.kts.txt", escape = "none")]
+pub(crate) struct KmpBuildScript<'a> {
+    pub(crate) namespace: &'a str,
+    pub(crate) min_sdk: u32,
+    pub(crate) compile_sdk: u32,
+    pub(crate) consumer_rules: &'a str,
+    /// Directory of the Kotlin bindings shared by the androidMain and jvmMain source sets
+    pub(crate) shared_sources: &'a str,
+    pub(crate) jna_version: &'a str,
+    pub(crate) coroutines_version: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "oh-package.json5.txt", escape = "none")]
+pub(crate) struct OhPackageJson5<'a> {
     pub(crate) package_name: &'a str,
+    pub(crate) version: &'a str,
+    pub(crate) description: &'a str,
+    pub(crate) license: &'a str,
+    pub(crate) lib_name: &'a str,
 }
 
 #[derive(Template)]
-#[template(path = "gradle-properties.txt", escape = "none")]
-pub(crate) struct GradleProperties<'a> {
+#[template(path = "oh-package-native.json5.txt", escape = "none")]
+pub(crate) struct NativeOhPackageJson5<'a> {
     pub(crate) lib_name: &'a str,
+    pub(crate) version: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "module.json5.txt", escape = "none")]
+pub(crate) struct ModuleJson5<'a> {
+    pub(crate) module_name: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "Index.ets.txt", escape = "none")]
+pub(crate) struct IndexEts<'a> {
+    pub(crate) lib_name: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "package.json.txt", escape = "none")]
+pub(crate) struct PackageJson<'a> {
+    pub(crate) package_name: &'a str,
+    pub(crate) version: &'a str,
+    pub(crate) description: &'a str,
+    pub(crate) license: &'a str,
+    pub(crate) lib_name: &'a str,
+    pub(crate) is_es_module: bool,
+    pub(crate) files: &'a [String],
+}
+
+#[derive(Template)]
+#[template(path = "pom.xml.txt", escape = "html")]
+pub(crate) struct PomXml<'a> {
+    pub(crate) group_id: &'a str,
+    pub(crate) artifact_id: &'a str,
+    pub(crate) version: &'a str,
+    pub(crate) name: &'a str,
+    pub(crate) description: Option<&'a str>,
+    pub(crate) jna_version: &'a str,
+    pub(crate) coroutines_version: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "maven-metadata.xml.txt", escape = "html")]
+pub(crate) struct MavenMetadataXml<'a> {
+    pub(crate) group_id: &'a str,
+    pub(crate) artifact_id: &'a str,
+    pub(crate) latest: &'a str,
+    /// Unset if only snapshots have been published
+    pub(crate) release: Option<&'a str>,
+    pub(crate) versions: &'a [String],
+    pub(crate) last_updated: &'a str,
+}
+
+#[derive(Template)]
+#[template(path = "proguard-rules.pro.txt", escape = "none")]
+pub(crate) struct ProguardRules<'a> {
+    pub(crate) package_name: &'a str,
+    pub(crate) structures: &'a [String],
+    pub(crate) callbacks: &'a [String],
+    pub(crate) callback_interfaces: &'a [ProguardCallbackInterface],
+    pub(crate) user_rules: Option<&'a str>,
+}
+
+pub(crate) struct ProguardCallbackInterface {
+    /// Name of the Kotlin interface
+    pub(crate) name: String,
+    /// Name of the object implementing the vtable passed to Rust
+    pub(crate) vtable_impl: String,
 }
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{anyhow, Context};
+use dialoguer::Confirm;
+use execute::command;
+
+use crate::common::models::Config;
+use crate::console::{step::run_step_with_commands, theme::prompt_theme};
+use crate::Result;
+
+/// Returns whether `program` can be executed on the host
+pub(crate) fn is_installed(program: &str, version_arg: &str) -> bool {
+    Command::new(program)
+        .arg(version_arg)
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Returns the first line printed by `program <version_arg>`, if the program is installed
+pub(crate) fn tool_version(program: &str, version_arg: &str) -> Option<String> {
+    let output = Command::new(program).arg(version_arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stdout
+        .lines()
+        .chain(stderr.lines())
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Returns the Rust targets whose standard library is installed for the active toolchain
+///
+/// Uses `rustup target list --installed` and falls back to the sysroot of `rustc` if rustup is not
+/// available.
+pub(crate) fn installed_targets() -> Result<Vec<String>> {
+    if let Ok(output) = Command::new("rustup")
+        .args(["target", "list", "--installed"])
+        .output()
+    {
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect());
+        }
+    }
+
+    let output = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .context("Could not run rustc to determine the installed targets")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Could not determine the sysroot of rustc: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let sysroot = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim());
+    let targets = std::fs::read_dir(sysroot.join("lib").join("rustlib"))
+        .context("Could not read the installed targets from the rustc sysroot")?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("lib").is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+
+    Ok(targets)
+}
+
+/// Returns whether a nightly toolchain with the `rust-src` component is installed
+///
+/// Both are needed to build the standard library of tier 3 targets with `-Z build-std`.
+pub(crate) fn has_nightly_with_rust_src() -> bool {
+    Command::new("rustup")
+        .args(["component", "list", "--installed", "--toolchain", "nightly"])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success()
+                && String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .any(|line| line.trim().starts_with("rust-src"))
+        })
+}
+
+/// Makes sure the standard library of all given targets is installed before building
+///
+/// Missing targets are installed with rustup after asking the user, or right away if all prompts are
+/// accepted. Tier 3 targets built with `-Z build-std` must not be passed here, as rustup does not
+/// distribute their standard library.
+pub(crate) fn ensure_targets_installed(targets: &[&str], config: &Config) -> Result<()> {
+    let installed = installed_targets()?;
+    let missing: Vec<_> = targets
+        .iter()
+        .filter(|target| !installed.iter().any(|t| t == *target))
+        .copied()
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let list = missing.join(", ");
+    if !is_installed("rustup", "--version") {
+        return Err(anyhow!(
+            "The standard library of the following targets is not installed: {list}. Please install it for your Rust toolchain."
+        ));
+    }
+
+    let install = config.accept_all
+        || Confirm::with_theme(&prompt_theme())
+            .with_prompt(format!("Install missing Rust targets {list} with rustup?"))
+            .default(true)
+            .interact()
+            .unwrap_or(false);
+
+    if !install {
+        return Err(anyhow!(
+            "The following Rust targets are not installed: {list}. Install them with `rustup target add {}`.",
+            missing.join(" ")
+        ));
+    }
+
+    let mut rustup = command("rustup target add");
+    rustup.args(&missing);
+    run_step_with_commands(config, "Installing Rust targets...", &mut [rustup])
+}
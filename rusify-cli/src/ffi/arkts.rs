@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use anyhow::Context;
+use camino::Utf8Path;
+use serde::Deserialize;
+
+use crate::common::models::Config;
+use crate::console::{messages::warning, step::run_step};
+use crate::Result;
+
+/// A single type definition as emitted by `napi_derive_ohos` while compiling the crate
+#[derive(Deserialize)]
+struct TypeDef {
+    kind: String,
+    name: String,
+    #[serde(default)]
+    js_doc: String,
+    def: String,
+}
+
+pub(crate) fn generate_arkts_declarations_with_output(
+    type_def_dir: &Utf8Path,
+    lib_name: &str,
+    config: &Config,
+) -> Result<()> {
+    let declarations = run_step(config, "Generating ArkTS declarations...", || {
+        generate_arkts_declarations(type_def_dir, lib_name, &config.generated_dir()).map_err(|e| {
+            anyhow::anyhow!(
+                "Could not generate ArkTS declarations due to the following error: \n {e}"
+            )
+        })
+    })?;
+
+    if declarations == 0 {
+        warning!(
+            config,
+            "No type definitions were emitted by napi. If the crate was not recompiled, run `cargo clean` and build again."
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts the napi type definitions found in `type_def_dir` to `arkts/index.d.ts` in `generated_dir`
+///
+/// Returns the number of top-level declarations written.
+pub fn generate_arkts_declarations(
+    type_def_dir: &Utf8Path,
+    lib_name: &str,
+    generated_dir: &Utf8Path,
+) -> Result<usize> {
+    let out_dir = generated_dir.join("arkts");
+    fs::create_dir_all(&out_dir)?;
+
+    let mut type_defs = Vec::new();
+    if type_def_dir.exists() {
+        for entry in fs::read_dir(type_def_dir)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read type definitions {}", path.display()))?;
+            for line in content.lines() {
+                // Newer napi versions prefix each line with the crate name
+                let Some(start) = line.find('{') else {
+                    continue;
+                };
+                let type_def: TypeDef = serde_json::from_str(&line[start..])
+                    .with_context(|| format!("Invalid type definition: {line}"))?;
+                type_defs.push(type_def);
+            }
+        }
+    }
+
+    // Methods of a class are emitted separately as `impl` blocks and merged into the class body
+    let mut impls: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for type_def in type_defs.iter().filter(|t| t.kind == "impl") {
+        impls
+            .entry(type_def.name.clone())
+            .or_default()
+            .push(type_def.def.clone());
+    }
+
+    let mut declarations = Vec::new();
+    for type_def in type_defs.iter().filter(|t| t.kind != "impl") {
+        let body = indent(&type_def.def);
+        let declaration = match type_def.kind.as_str() {
+            "fn" => format!("export declare function {}", type_def.def),
+            "const" => format!("export declare const {}", type_def.def),
+            "type" => format!("export type {}", type_def.def),
+            "enum" => format!("export declare const enum {} {{\n{}\n}}", type_def.name, body),
+            "interface" => format!("export interface {} {{\n{}\n}}", type_def.name, body),
+            _ => {
+                let mut members = vec![type_def.def.clone()];
+                members.extend(impls.remove(&type_def.name).unwrap_or_default());
+                let members = members
+                    .iter()
+                    .filter(|m| !m.is_empty())
+                    .map(|m| indent(m))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                format!("export declare class {} {{\n{}\n}}", type_def.name, members)
+            }
+        };
+
+        declarations.push(format!("{}{}", js_doc(&type_def.js_doc), declaration));
+    }
+
+    let count = declarations.len();
+    let header = format!("/* Auto-generated type declarations for lib{lib_name}.so */\n");
+    fs::write(
+        out_dir.join("index.d.ts"),
+        header + &declarations.join("\n\n") + "\n",
+    )?;
+
+    Ok(count)
+}
+
+fn indent(def: &str) -> String {
+    def.lines()
+        .map(|line| format!("  {}", line.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn js_doc(doc: &str) -> String {
+    if doc.trim().is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", doc.trim_end())
+    }
+}
@@ -1,15 +1,17 @@
 use anyhow::Result;
 use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::Metadata;
 use std::fs::{self, create_dir};
 use std::path::Path;
 
 use crate::common::path::recreate_dir;
-use crate::common::metadata::{metadata, MetadataExt};
+use crate::common::metadata::Workspace;
 use crate::android::android_target::AndroidTarget;
 use crate::console::step::run_step;
 use crate::common::models::{Config, LibType, Mode};
 
 pub(crate) fn generate_kotlin_bindings_with_output(
+    workspace: &Workspace,
     targets: &[AndroidTarget],
     lib_name: &str,
     mode: Mode,
@@ -19,7 +21,7 @@ pub(crate) fn generate_kotlin_bindings_with_output(
 ) -> Result<()> {
     run_step(config, "Generating Kotlin bindings...", || {
         let lib_file = library_file_name(lib_name, lib_type);
-        let target = metadata().target_dir();
+        let target = workspace.target_dir();
         let archs = targets
             .first()
             .ok_or_else(|| anyhow::anyhow!("Could not generate UniFFI bindings: No target architecture selected!"))?
@@ -32,13 +34,17 @@ pub(crate) fn generate_kotlin_bindings_with_output(
             return Err(anyhow::anyhow!("Library file does not exist: {}", lib_path));
         }
 
-        generate_kotlin_bindings(&lib_path, package_name)
+        generate_kotlin_bindings(&workspace.metadata, &lib_path, package_name, &config.generated_dir())
             .map_err(|e| anyhow::anyhow!("Could not generate UniFFI bindings for Kotlin due to the following error: \n {e}"))
     })
 }
 
-pub fn generate_kotlin_bindings(lib_path: &Utf8Path, package_name: &str) -> Result<()> {
-    let out_dir = Utf8Path::new("./generated");
+pub fn generate_kotlin_bindings(
+    metadata: &Metadata,
+    lib_path: &Utf8Path,
+    package_name: &str,
+    out_dir: &Utf8Path,
+) -> Result<()> {
     let kotlin_dir = out_dir.join("kotlin");
     
     recreate_dir(out_dir)?;
@@ -64,7 +70,7 @@ pub fn generate_kotlin_bindings(lib_path: &Utf8Path, package_name: &str) -> Resu
         lib_path,
         None,
         &binding_generator,
-        &uniffi_bindgen::cargo_metadata::CrateConfigSupplier::from(metadata().clone()),
+        &uniffi_bindgen::cargo_metadata::CrateConfigSupplier::from(metadata.clone()),
         Some(&config_file),
         out_dir,
         false,
@@ -85,17 +91,15 @@ pub fn generate_kotlin_bindings(lib_path: &Utf8Path, package_name: &str) -> Resu
         println!("Found generated package directory: {}", expected_package_dir);
         let mut found_files = 0;
         if let Ok(entries) = fs::read_dir(&expected_package_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    if entry.path().extension().map_or(false, |ext| ext == "kt") {
-                        found_files += 1;
-                        println!("  Copying Kotlin file: {}", entry.path().display());
-                        let path = entry.path();
-                        let file_name = path.file_name().unwrap();
-                        let file_name_str = file_name.to_string_lossy();
-                        let dest_file = package_dir.join(file_name_str.as_ref());
-                        fs::copy(entry.path(), dest_file)?;
-                    }
+            for entry in entries.flatten() {
+                if entry.path().extension().is_some_and(|ext| ext == "kt") {
+                    found_files += 1;
+                    println!("  Copying Kotlin file: {}", entry.path().display());
+                    let path = entry.path();
+                    let file_name = path.file_name().unwrap();
+                    let file_name_str = file_name.to_string_lossy();
+                    let dest_file = package_dir.join(file_name_str.as_ref());
+                    fs::copy(entry.path(), dest_file)?;
                 }
             }
         }
@@ -109,17 +113,15 @@ pub fn generate_kotlin_bindings(lib_path: &Utf8Path, package_name: &str) -> Resu
         println!("Checking for Kotlin files in the root output directory...");
         let mut found_files = 0;
         if let Ok(entries) = fs::read_dir(out_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    if entry.path().extension().map_or(false, |ext| ext == "kt") {
-                        found_files += 1;
-                        println!("  Copying Kotlin file: {}", entry.path().display());
-                        let path = entry.path();
-                        let file_name = path.file_name().unwrap();
-                        let file_name_str = file_name.to_string_lossy();
-                        let dest_file = package_dir.join(file_name_str.as_ref());
-                        fs::copy(entry.path(), dest_file)?;
-                    }
+            for entry in entries.flatten() {
+                if entry.path().extension().is_some_and(|ext| ext == "kt") {
+                    found_files += 1;
+                    println!("  Copying Kotlin file: {}", entry.path().display());
+                    let path = entry.path();
+                    let file_name = path.file_name().unwrap();
+                    let file_name_str = file_name.to_string_lossy();
+                    let dest_file = package_dir.join(file_name_str.as_ref());
+                    fs::copy(entry.path(), dest_file)?;
                 }
             }
         }
@@ -145,18 +147,14 @@ pub fn generate_kotlin_bindings(lib_path: &Utf8Path, package_name: &str) -> Resu
                 // List all files in the output directory to help debug
                 println!("Files in {}:", out_dir);
                 if let Ok(entries) = fs::read_dir(out_dir) {
-                    for entry in entries {
-                        if let Ok(entry) = entry {
-                            println!("  {}", entry.path().display());
-                            
-                            // If it's a directory, list its contents
-                            if entry.path().is_dir() {
-                                if let Ok(sub_entries) = fs::read_dir(entry.path()) {
-                                    for sub_entry in sub_entries {
-                                        if let Ok(sub_entry) = sub_entry {
-                                            println!("    {}", sub_entry.path().display());
-                                        }
-                                    }
+                    for entry in entries.flatten() {
+                        println!("  {}", entry.path().display());
+                        
+                        // If it's a directory, list its contents
+                        if entry.path().is_dir() {
+                            if let Ok(sub_entries) = fs::read_dir(entry.path()) {
+                                for sub_entry in sub_entries.flatten() {
+                                    println!("    {}", sub_entry.path().display());
                                 }
                             }
                         }
@@ -2,6 +2,7 @@ use std::io;
 use std::fs::{self, create_dir};
 
 use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::Metadata;
 use uniffi_bindgen::{
     bindings::SwiftBindingGenerator,
     cargo_metadata::CrateConfigSupplier,
@@ -10,16 +11,16 @@ use uniffi_bindgen::{
 use crate::{
     apple::apple_target::{AppleTarget, library_file_name},
     common::{
-        metadata::MetadataExt,
+        metadata::Workspace,
         models::{Config, LibType, Mode},
         path::recreate_dir,
     },
     console::step::run_step,
-    metadata::metadata,
     Result,
 };
 
 pub(crate) fn generate_swift_bindings_with_output(
+    workspace: &Workspace,
     targets: &[AppleTarget],
     lib_name: &str,
     mode: Mode,
@@ -28,7 +29,7 @@ pub(crate) fn generate_swift_bindings_with_output(
 ) -> Result<()> {
     run_step(config, "Generating Swift bindings...", || {
         let lib_file = library_file_name(lib_name, lib_type);
-        let target = metadata().target_dir();
+        let target = workspace.target_dir();
         let archs = targets
             .first()
             .ok_or_else(|| anyhow::anyhow!("Could not generate UniFFI bindings: No target platform selected!"))?
@@ -36,13 +37,16 @@ pub(crate) fn generate_swift_bindings_with_output(
         let arch = archs.first().ok_or_else(|| anyhow::anyhow!("No architectures found for the selected target"))?;
         let lib_path: Utf8PathBuf = format!("{}/{}/{}/{}", target, arch, mode, lib_file).into();
 
-        generate_swift_bindings(&lib_path)
+        generate_swift_bindings(&workspace.metadata, &lib_path, &config.generated_dir())
             .map_err(|e| anyhow::anyhow!("Could not generate UniFFI bindings for udl files due to the following error: \n {e}"))
     })
 }
 
-pub fn generate_swift_bindings(lib_path: &Utf8Path) -> Result<()> {
-    let out_dir = Utf8Path::new("./generated");
+pub fn generate_swift_bindings(
+    metadata: &Metadata,
+    lib_path: &Utf8Path,
+    out_dir: &Utf8Path,
+) -> Result<()> {
     let headers = out_dir.join("headers");
     let sources = out_dir.join("sources");
 
@@ -54,7 +58,7 @@ pub fn generate_swift_bindings(lib_path: &Utf8Path) -> Result<()> {
         lib_path,
         None,
         &SwiftBindingGenerator {},
-        &CrateConfigSupplier::from(metadata().clone()),
+        &CrateConfigSupplier::from(metadata.clone()),
         None,
         out_dir,
         false,
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use wasm_bindgen_cli_support::Bindgen;
+
+use crate::common::models::Config;
+use crate::console::step::run_step;
+use crate::wasm::wasm_target::WasmFlavor;
+use crate::Result;
+
+/// Generates the JavaScript glue code and TypeScript declarations for the given .wasm file
+///
+/// The bindings are generated in-process, which requires the crate to depend on the exact
+/// wasm-bindgen version rusify is built against (see the `init` template).
+pub(crate) fn generate_typescript_bindings_with_output(
+    wasm_path: &str,
+    lib_name: &str,
+    flavor: WasmFlavor,
+    out_dir: &Path,
+    config: &Config,
+) -> Result<()> {
+    run_step(config, "Generating JavaScript and TypeScript bindings...", || {
+        let mut bindgen = Bindgen::new();
+        bindgen
+            .input_path(wasm_path)
+            .out_name(lib_name)
+            .typescript(true);
+
+        match flavor {
+            WasmFlavor::Web => bindgen.web(true)?,
+            WasmFlavor::Bundler => bindgen.bundler(true)?,
+            WasmFlavor::NodeJs => bindgen.nodejs(true)?,
+        };
+
+        bindgen.generate(out_dir)
+    })
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Could not generate wasm-bindgen bindings due to the following error: \n {e:#}"
+        )
+    })
+}
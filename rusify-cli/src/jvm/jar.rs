@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use camino::Utf8Path;
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+use crate::android::jar::{compile_kotlin_with_output, zip_dir};
+use crate::common::models::{Config, Mode};
+use crate::common::path::recreate_dir;
+use crate::console::{messages::info, step::run_step};
+use crate::jvm::jvm_target::JvmTarget;
+
+/// Creates `<jar_name>.jar` with the compiled Kotlin bindings and the native libraries of all targets
+///
+/// The sources of the bindings are written to `<jar_name>-sources.jar` next to it.
+pub(crate) fn create_jar_with_output(
+    targets: &[JvmTarget],
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    jar_name: &str,
+    classpath: &[PathBuf],
+    mode: Mode,
+    config: &Config,
+) -> Result<()> {
+    let sources_dir = config.generated_dir().join("kotlin").into_std_path_buf();
+    let staging_dir = target_dir
+        .join("rusify")
+        .join("jvm")
+        .join(format!("{lib_name}-classes"))
+        .into_std_path_buf();
+    // Classes of a previous build must not end up in the jar if kotlinc is not available anymore
+    recreate_dir(&staging_dir)?;
+    compile_kotlin_with_output(&sources_dir, &staging_dir, classpath, config)?;
+
+    let jar_path = config.output_path(&format!("{jar_name}.jar"));
+    run_step(config, "Creating JAR...", || {
+        // Without kotlinc, the jar only contains the native libraries
+        copy_jvm_libraries(targets, target_dir, lib_name, mode, &staging_dir)?;
+
+        if let Some(parent) = jar_path.parent() {
+            create_dir_all(parent)?;
+        }
+        zip_dir(&staging_dir, jar_path.as_std_path(), true).context("Failed to create the JAR")?;
+
+        let sources_jar = config.output_path(&format!("{jar_name}-sources.jar"));
+        zip_dir(&sources_dir, sources_jar.as_std_path(), true)
+            .context("Failed to create the sources jar")?;
+        Ok(())
+    })
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create JAR due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    info!(config, "Created JAR at: {jar_path}");
+
+    Ok(())
+}
+
+/// Copies the native libraries of all targets into `<resources_dir>/<os>-<arch>`
+///
+/// JNA extracts libraries from these classpath directories if they are not found on the library path.
+pub(crate) fn copy_jvm_libraries(
+    targets: &[JvmTarget],
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    mode: Mode,
+    resources_dir: &Path,
+) -> Result<()> {
+    for target in targets {
+        let platform_dir = resources_dir.join(target.jna_resource_prefix());
+        recreate_dir(&platform_dir)?;
+
+        fs::copy(
+            target.library_path(target_dir, lib_name, mode),
+            platform_dir.join(target.library_file_name(lib_name)),
+        )
+        .with_context(|| format!("Failed to copy library for {}", target.display_name()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::process::Command;
+
+use camino::{Utf8Path, Utf8PathBuf};
+use execute::command;
+
+use crate::common::models::{FeatureOptions, Mode};
+
+const TARGET_COUNT: usize = 6;
+
+/// Desktop platforms the native library is bundled for in the JAR
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JvmTarget {
+    #[value(name = "linux-x86-64")]
+    LinuxX86_64,
+    #[value(name = "linux-arm64")]
+    LinuxArm64,
+    #[value(name = "macos-x86-64")]
+    MacosX86_64,
+    #[value(name = "macos-arm64")]
+    MacosArm64,
+    #[value(name = "windows-x86-64")]
+    WindowsX86_64,
+    #[value(name = "windows-arm64")]
+    WindowsArm64,
+}
+
+impl JvmTarget {
+    pub(crate) fn display_name(&self) -> &'static str {
+        match self {
+            JvmTarget::LinuxX86_64 => "Linux x86_64",
+            JvmTarget::LinuxArm64 => "Linux ARM64",
+            JvmTarget::MacosX86_64 => "macOS x86_64",
+            JvmTarget::MacosArm64 => "macOS ARM64",
+            JvmTarget::WindowsX86_64 => "Windows x86_64",
+            JvmTarget::WindowsArm64 => "Windows ARM64",
+        }
+    }
+
+    pub(crate) fn all() -> [Self; TARGET_COUNT] {
+        [
+            Self::LinuxX86_64,
+            Self::LinuxArm64,
+            Self::MacosX86_64,
+            Self::MacosArm64,
+            Self::WindowsX86_64,
+            Self::WindowsArm64,
+        ]
+    }
+
+    /// Returns the target of the host, which can be built without a cross-compilation toolchain
+    pub(crate) fn host() -> Option<Self> {
+        Self::all().into_iter().find(|target| {
+            target.triple().starts_with(std::env::consts::ARCH)
+                && target.os() == std::env::consts::OS
+        })
+    }
+
+    /// The identifier of the official Rust target
+    pub fn triple(&self) -> &'static str {
+        match self {
+            JvmTarget::LinuxX86_64 => "x86_64-unknown-linux-gnu",
+            JvmTarget::LinuxArm64 => "aarch64-unknown-linux-gnu",
+            JvmTarget::MacosX86_64 => "x86_64-apple-darwin",
+            JvmTarget::MacosArm64 => "aarch64-apple-darwin",
+            JvmTarget::WindowsX86_64 => "x86_64-pc-windows-msvc",
+            JvmTarget::WindowsArm64 => "aarch64-pc-windows-msvc",
+        }
+    }
+
+    fn os(&self) -> &'static str {
+        match self {
+            JvmTarget::LinuxX86_64 | JvmTarget::LinuxArm64 => "linux",
+            JvmTarget::MacosX86_64 | JvmTarget::MacosArm64 => "macos",
+            JvmTarget::WindowsX86_64 | JvmTarget::WindowsArm64 => "windows",
+        }
+    }
+
+    /// The directory JNA loads the library of this platform from when it is bundled as a resource
+    pub fn jna_resource_prefix(&self) -> &'static str {
+        match self {
+            JvmTarget::LinuxX86_64 => "linux-x86-64",
+            JvmTarget::LinuxArm64 => "linux-aarch64",
+            JvmTarget::MacosX86_64 => "darwin-x86-64",
+            JvmTarget::MacosArm64 => "darwin-aarch64",
+            JvmTarget::WindowsX86_64 => "win32-x86-64",
+            JvmTarget::WindowsArm64 => "win32-aarch64",
+        }
+    }
+
+    /// The file name of the dynamic library, following the conventions of the platform
+    pub fn library_file_name(&self, lib_name: &str) -> String {
+        match self.os() {
+            "macos" => format!("lib{lib_name}.dylib"),
+            "windows" => format!("{lib_name}.dll"),
+            _ => format!("lib{lib_name}.so"),
+        }
+    }
+
+    pub fn library_path(&self, target_dir: &Utf8Path, lib_name: &str, mode: Mode) -> Utf8PathBuf {
+        target_dir
+            .join(self.triple())
+            .join(mode.to_string())
+            .join(self.library_file_name(lib_name))
+    }
+
+    pub(crate) fn cargo_build_command(
+        &self,
+        manifest_path: &Utf8Path,
+        mode: Mode,
+        features: &FeatureOptions,
+    ) -> Command {
+        let mut cmd = command("cargo build");
+        cmd.arg("--manifest-path").arg(manifest_path);
+        cmd.arg("--target").arg(self.triple());
+
+        match mode {
+            Mode::Debug => {}
+            Mode::Release => {
+                cmd.arg("--release");
+            }
+        }
+
+        if let Some(features) = &features.features {
+            cmd.arg("--features").arg(features.join(","));
+        }
+        if features.all_features {
+            cmd.arg("--all-features");
+        }
+        if features.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+
+        cmd
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{anyhow, Result};
+use camino::Utf8Path;
+use cargo_metadata::Package;
+use dialoguer::{Input, MultiSelect};
+
+use crate::common::{
+    metadata::{library_target, Workspace},
+    models::{default_output_name, Config, CrateSelection, FeatureArgs, FeatureOptions, Mode},
+    project::ProjectConfig,
+    toolchain::ensure_targets_installed,
+};
+use crate::console::{
+    messages::*,
+    step::{run_step, run_step_with_commands},
+    theme::prompt_theme,
+};
+use crate::ffi::kotlin::generate_kotlin_bindings;
+use crate::jvm::jar::create_jar_with_output;
+use crate::jvm::jvm_target::JvmTarget;
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_jvm_package(
+    workspace: &Workspace,
+    selection: &CrateSelection,
+    targets: Option<Vec<JvmTarget>>,
+    package_name: Option<String>,
+    jar_name: Option<String>,
+    config: Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let crates = workspace.selected_crates(selection)?;
+
+    if crates.len() == 1 {
+        return build_jvm_package_for_crate(
+            workspace,
+            crates[0],
+            targets,
+            package_name,
+            jar_name,
+            false,
+            &config,
+            mode,
+            features,
+        );
+    } else if package_name.is_some() {
+        return Err(anyhow!(
+            "Package name can only be specified when building a single crate!"
+        ));
+    } else if jar_name.is_some() {
+        return Err(anyhow!(
+            "JAR name can only be specified when building a single crate!"
+        ));
+    }
+
+    crates
+        .iter()
+        .map(|current_crate| {
+            info!(&config, "Packaging crate {}", current_crate.name);
+            build_jvm_package_for_crate(
+                workspace,
+                current_crate,
+                targets.clone(),
+                None,
+                None,
+                true,
+                &config,
+                mode,
+                features.clone(),
+            )
+        })
+        .filter_map(|result| result.err())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .reduce(|acc, err| acc.context(err))
+        .map_or(Ok(()), Err)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_jvm_package_for_crate(
+    workspace: &Workspace,
+    current_crate: &Package,
+    targets: Option<Vec<JvmTarget>>,
+    package_name: Option<String>,
+    jar_name: Option<String>,
+    multiple_crates: bool,
+    config: &Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let lib = library_target(current_crate)?;
+
+    let project = ProjectConfig::load(current_crate, config)?;
+
+    let mode = project.mode(mode);
+    let features = project.features(features);
+    let jar_name = jar_name
+        .or(project.jvm.jar_name)
+        .unwrap_or_else(|| default_output_name("RustLibrary", current_crate, multiple_crates));
+
+    let crate_dir = current_crate
+        .manifest_path
+        .parent()
+        .expect("The Cargo.toml path should end with /Cargo.toml");
+
+    let crate_name = current_crate.name.to_lowercase();
+    let default_package_name = format!("com.{}.{}", crate_name, crate_name);
+    let package_name = package_name
+        .or(project.jvm.package_name)
+        .unwrap_or_else(|| prompt_package_name(&default_package_name, config.accept_all));
+
+    let targets = targets
+        .or(project.jvm.targets)
+        .unwrap_or_else(|| prompt_targets(config.accept_all));
+
+    if targets.is_empty() {
+        return Err(anyhow!("At least 1 target needs to be selected!"));
+    }
+
+    let target_dir = workspace.target_dir();
+    let crate_name = lib.name.replace('-', "_");
+    build_jvm_libraries_with_output(
+        &targets,
+        &current_crate.manifest_path,
+        &target_dir,
+        &crate_name,
+        mode,
+        &features,
+        config,
+    )?;
+
+    let lib_path = targets[0].library_path(&target_dir, &crate_name, mode);
+    run_step(config, "Generating Kotlin bindings...", || {
+        generate_kotlin_bindings(&workspace.metadata, &lib_path, &package_name, &config.generated_dir())
+            .map_err(|e| anyhow!("Could not generate UniFFI bindings for Kotlin due to the following error: \n {e}"))
+    })?;
+
+    let classpath: Vec<_> = project
+        .jvm
+        .kotlin_classpath
+        .iter()
+        .flatten()
+        .map(|jar| crate_dir.as_std_path().join(jar))
+        .collect();
+
+    create_jar_with_output(
+        &targets,
+        &target_dir,
+        &crate_name,
+        &jar_name,
+        &classpath,
+        mode,
+        config,
+    )
+}
+
+/// Builds the dynamic library for every JVM target
+///
+/// Targets other than the host require a cross-compilation toolchain, e.g. a linker configured in
+/// `.cargo/config.toml`.
+pub(crate) fn build_jvm_libraries_with_output(
+    targets: &[JvmTarget],
+    manifest_path: &Utf8Path,
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    mode: Mode,
+    features: &FeatureOptions,
+    config: &Config,
+) -> Result<()> {
+    let triples: Vec<_> = targets.iter().map(|target| target.triple()).collect();
+    ensure_targets_installed(&triples, config)?;
+
+    let host = JvmTarget::host();
+    for target in targets {
+        if Some(*target) != host {
+            warning!(
+                config,
+                "{} is not the host platform, building it requires a cross-compilation toolchain.",
+                target.display_name()
+            );
+        }
+
+        let mut command = target.cargo_build_command(manifest_path, mode, features);
+        command.env("CARGO_TERM_COLOR", "always");
+        run_step_with_commands(
+            config,
+            format!("Building target {}", target.display_name()),
+            &mut [command],
+        )?;
+
+        let lib_path = target.library_path(target_dir, lib_name, mode);
+        if !lib_path.exists() {
+            return Err(anyhow!(
+                "Library file does not exist: {lib_path}. Make sure the crate-type in Cargo.toml includes \"cdylib\"."
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn prompt_targets(accept_all: bool) -> Vec<JvmTarget> {
+    let targets = JvmTarget::all();
+    let host = JvmTarget::host();
+
+    if accept_all {
+        return host.into_iter().collect();
+    }
+
+    let items = targets.map(|t| t.display_name());
+    let defaults = targets.map(|t| Some(t) == host);
+
+    let theme = prompt_theme();
+    let selector = MultiSelect::with_theme(&theme)
+        .items(&items)
+        .with_prompt("Select Target Platforms")
+        .defaults(&defaults); // Default to the host, others require cross-compilation
+
+    let chosen: Vec<usize> = selector.interact().unwrap();
+
+    chosen.into_iter().map(|i| targets[i]).collect()
+}
+
+fn prompt_package_name(default: &str, accept_all: bool) -> String {
+    if accept_all {
+        return default.to_string();
+    }
+
+    let theme = prompt_theme();
+    Input::with_theme(&theme)
+        .with_prompt("Kotlin Package Name")
+        .default(default.to_string())
+        .interact_text()
+        .unwrap()
+}
@@ -1,8 +1,12 @@
 mod commands {
     pub mod init;
+    pub mod build;
+    pub mod doctor;
 }
 pub mod apple {
     pub mod apple_target;
+    pub mod distribution;
+    pub mod macho;
     pub mod package;
     pub mod swiftpackage;
     pub mod xcframework;
@@ -11,9 +15,28 @@ pub mod android {
     pub mod android_target;
     pub mod package;
     pub mod aar;
+    pub mod ndk;
+    pub mod elf;
+    pub mod jar;
+    pub mod gradle;
+    pub mod maven;
+    pub mod manifest;
+    pub mod proguard;
+}
+pub mod jvm {
+    pub mod jvm_target;
+    pub mod package;
+    pub mod jar;
 }
 pub mod ohos {
     pub mod ohos_arch;
+    pub mod package;
+    pub mod har;
+}
+pub mod wasm {
+    pub mod wasm_target;
+    pub mod package;
+    pub mod npm;
 }
 pub(crate) mod console {
     pub mod step;
@@ -34,10 +57,15 @@ mod common {
     pub mod path;
     pub mod models;
     pub mod templating;
+    pub mod project;
+    pub mod platform;
+    pub mod toolchain;
 }
 mod ffi {
     pub mod swift;
     pub mod kotlin;
+    pub mod arkts;
+    pub mod typescript;
 }
 
 pub use commands::*;
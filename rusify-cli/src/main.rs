@@ -1,14 +1,37 @@
-use clap::{Parser, Subcommand};
-use rusify_cli::android::android_target::AndroidArch;
 use std::process::ExitCode;
+
+use camino::Utf8PathBuf;
+use clap::{Parser, Subcommand};
+use rusify_cli::android::android_target::{AndroidArch, AndroidOutput};
 use rusify_cli::apple::apple_target::ApplePlatform;
-use rusify_cli::models::{LibType, Mode, FeatureOptions, Config};
+use rusify_cli::apple::distribution::Distribution;
+use rusify_cli::jvm::jvm_target::JvmTarget;
+use rusify_cli::ohos::ohos_arch::OhosArch;
+use rusify_cli::wasm::wasm_target::WasmFlavor;
+use rusify_cli::models::{LibType, Mode, FeatureArgs, Config, CrateSelection};
+use rusify_cli::metadata::{MetadataOptions, Workspace};
+use rusify_cli::platform::Platform;
+use rusify_cli::Result;
 
+/// Command line values take precedence over the configuration in `rusify.toml` or
+/// `[package.metadata.rusify]` of the crate, which in turn take precedence over the defaults.
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands, // 将 Option<Commands> 改为 Commands
+
+    #[arg(long, global = true)]
+    /// Path to the Cargo.toml of the crate or workspace to package (default: current directory)
+    manifest_path: Option<Utf8PathBuf>,
+
+    #[arg(long, global = true, default_value = ".")]
+    /// Directory packages and generated bindings are written to
+    out_dir: Utf8PathBuf,
+
+    #[arg(long, global = true)]
+    /// Allow cargo to access the network while reading the workspace (default: offline)
+    online: bool,
 }
 
 #[derive(Subcommand)]
@@ -18,7 +41,7 @@ enum Commands {
         crate_name: String,
     },
     Build {
-        #[arg(short, long, trailing_var_arg = true, num_args = 1..=4, ignore_case = true)]
+        #[arg(short = 'P', long, trailing_var_arg = true, num_args = 1..=4, ignore_case = true)]
         platforms: Option<Vec<ApplePlatform>>,
 
         #[arg(long)]
@@ -28,30 +51,79 @@ enum Commands {
         #[arg(short = 'n', long = "name")]
         package_name: Option<String>,
 
-        #[arg(long, default_value = "RustFramework")]
-        xcframework_name: String,
+        #[arg(long)]
+        /// Name of the generated XCFramework (default: RustFramework, or <Crate>Framework for several crates)
+        xcframework_name: Option<String>,
+
+        #[arg(long = "deployment-target", value_parser = parse_deployment_target)]
+        /// Minimum version of a platform declared in Package.swift, e.g. ios=15.0 (may be repeated)
+        deployment_targets: Vec<(ApplePlatform, String)>,
+
+        #[arg(long)]
+        /// swift-tools-version of the generated Package.swift (default: the oldest supporting all platforms)
+        swift_tools_version: Option<String>,
+
+        #[arg(long, ignore_case = true)]
+        /// How the XCFramework is referenced by Package.swift (default: local)
+        distribution: Option<Distribution>,
+
+        #[arg(long)]
+        /// URL the zipped XCFramework will be hosted at for remote distribution, e.g. https://example.com/v1.0.0
+        url_base: Option<String>,
+
+        #[arg(long, overrides_with = "no_apple_silicon_only")]
+        /// Build macOS and the iOS Simulator for Apple Silicon only, without x86_64 slices
+        apple_silicon_only: bool,
+
+        #[arg(long, overrides_with = "apple_silicon_only")]
+        /// Build the x86_64 slices even if Apple Silicon only is configured for the crate
+        no_apple_silicon_only: bool,
 
-        #[arg(short, long)]
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
+        #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
         /// Build package optimized for release (default: debug)
         release: bool,
 
-        #[arg(long, ignore_case = true, default_value_t = LibType::Static)]
-        /// Choose how the library should be built. By default, this will be derived from the lib type provided in Cargo.toml
-        lib_type: LibType,
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
 
-        #[arg(long)]
+        #[arg(long, ignore_case = true)]
+        /// Choose how the library should be built (default: static)
+        lib_type: Option<LibType>,
+
+        #[arg(long, overrides_with = "no_suppress_warnings")]
         /// Disable warnings in generated Swift package code
         suppress_warnings: bool,
 
+        #[arg(long, overrides_with = "suppress_warnings")]
+        /// Keep warnings in generated Swift package code even if suppressing them is configured for the crate
+        no_suppress_warnings: bool,
+
         #[arg(short = 'F', long, trailing_var_arg = true)]
         features: Option<Vec<String>>,
 
-        #[arg(long)]
+        #[arg(long, overrides_with = "no_all_features")]
         all_features: bool,
 
-        #[arg(long)]
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
         no_default_features: bool,
 
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
         #[arg(short, long, global = true)]
         /// Silence all output except errors and interactive prompts
         silent: bool,
@@ -72,33 +144,286 @@ enum Commands {
         /// Build package for the specified target triplet only.
         target: Option<String>,
 
-        #[arg(long, default_value_t = 21)]
-        /// Android API level to target (minimum 21 for 64-bit support)
-        api_level: u32,
+        #[arg(long)]
+        /// Android API level to target (minimum 21 for 64-bit support, default: 21)
+        api_level: Option<u32>,
+
+        #[arg(long)]
+        /// Version of the Android NDK to use, e.g. 26 or 26.1.10909125 (default: ANDROID_NDK_HOME or the newest installed)
+        ndk_version: Option<String>,
 
         #[arg(short = 'n', long = "name")]
         package_name: Option<String>,
 
-        #[arg(long, default_value = "RustLibrary")]
-        aar_name: String,
+        #[arg(long)]
+        /// Name of the generated AAR, Gradle or Kotlin Multiplatform module (default: RustLibrary)
+        aar_name: Option<String>,
+
+        #[arg(long, ignore_case = true)]
+        /// Kind of package to create (default: aar)
+        output: Option<AndroidOutput>,
 
-        #[arg(short, long)]
+        #[arg(long)]
+        /// Publish the AAR to the Maven repository in the given directory
+        maven_repo: Option<Utf8PathBuf>,
+
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
+        #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
         /// Build package optimized for release (default: debug)
         release: bool,
 
-        #[arg(long, ignore_case = true, default_value_t = LibType::Dynamic)]
-        /// Choose how the library should be built. For Android, dynamic (shared) libraries are recommended.
-        lib_type: LibType,
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
+
+        #[arg(long, ignore_case = true)]
+        /// Choose how the library should be built (default: dynamic). For Android, dynamic (shared) libraries are recommended.
+        lib_type: Option<LibType>,
 
         #[arg(short = 'F', long, trailing_var_arg = true)]
         features: Option<Vec<String>>,
 
+        #[arg(long, overrides_with = "no_all_features")]
+        all_features: bool,
+
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
+        no_default_features: bool,
+
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
+        #[arg(short, long, global = true)]
+        /// Silence all output except errors and interactive prompts
+        silent: bool,
+    
+        #[arg(short = 'y', long, global = true)]
+        /// Accept all default selections from all interactive prompts.
+        accept_all: bool,
+    },
+    /// Build a JAR bundling the native library for desktop JVM applications
+    BuildJvm {
+        #[arg(short, long, trailing_var_arg = true, num_args = 1..=6, ignore_case = true)]
+        /// Desktop platforms to bundle (default: the host)
+        targets: Option<Vec<JvmTarget>>,
+
+        #[arg(short = 'n', long = "name")]
+        package_name: Option<String>,
+
         #[arg(long)]
+        /// Name of the generated JAR (default: RustLibrary)
+        jar_name: Option<String>,
+
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
+        #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
+        /// Build package optimized for release (default: debug)
+        release: bool,
+
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
+
+        #[arg(short = 'F', long, trailing_var_arg = true)]
+        features: Option<Vec<String>>,
+
+        #[arg(long, overrides_with = "no_all_features")]
+        all_features: bool,
+
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
+        no_default_features: bool,
+
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
+        #[arg(short, long, global = true)]
+        /// Silence all output except errors and interactive prompts
+        silent: bool,
+    
+        #[arg(short = 'y', long, global = true)]
+        /// Accept all default selections from all interactive prompts.
+        accept_all: bool,
+    },
+    BuildOhos {
+        #[arg(short, long, trailing_var_arg = true, num_args = 1..=3, ignore_case = true)]
+        archs: Option<Vec<OhosArch>>,
+
+        #[arg(long)]
+        /// Build package for the specified target triplet only.
+        target: Option<String>,
+
+        #[arg(short = 'n', long = "name")]
+        package_name: Option<String>,
+
+        #[arg(long)]
+        /// Name of the generated HAR (default: RustHar)
+        har_name: Option<String>,
+
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
+        #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
+        /// Build package optimized for release (default: debug)
+        release: bool,
+
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
+
+        #[arg(short = 'F', long, trailing_var_arg = true)]
+        features: Option<Vec<String>>,
+
+        #[arg(long, overrides_with = "no_all_features")]
+        all_features: bool,
+
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
+        no_default_features: bool,
+
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
+        #[arg(short, long, global = true)]
+        /// Silence all output except errors and interactive prompts
+        silent: bool,
+    
+        #[arg(short = 'y', long, global = true)]
+        /// Accept all default selections from all interactive prompts.
+        accept_all: bool,
+    },
+    BuildWasm {
+        #[arg(long, ignore_case = true)]
+        /// The JavaScript module flavor generated by wasm-bindgen
+        flavor: Option<WasmFlavor>,
+
+        #[arg(short = 'n', long = "name")]
+        package_name: Option<String>,
+
+        #[arg(long)]
+        /// Directory the npm package is written to (default: pkg)
+        pkg_dir: Option<String>,
+
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
+        #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
+        /// Build package optimized for release (default: debug)
+        release: bool,
+
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
+
+        #[arg(short = 'F', long, trailing_var_arg = true)]
+        features: Option<Vec<String>>,
+
+        #[arg(long, overrides_with = "no_all_features")]
         all_features: bool,
 
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
+        no_default_features: bool,
+
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
+        #[arg(short, long, global = true)]
+        /// Silence all output except errors and interactive prompts
+        silent: bool,
+    
+        #[arg(short = 'y', long, global = true)]
+        /// Accept all default selections from all interactive prompts.
+        accept_all: bool,
+    },
+    /// Check the host environment and print which platforms are ready to be built
+    Doctor {
+        #[arg(short = 'P', long, value_delimiter = ',', num_args = 1..=5, ignore_case = true)]
+        /// Platforms to check (default: all)
+        platforms: Option<Vec<Platform>>,
+
+        #[arg(long)]
+        /// Exit with an error if any of the checked platforms is not ready
+        strict: bool,
+    },
+    /// Build packages for several platforms in one run, skipping platforms without an available toolchain
+    BuildAll {
+        #[arg(short = 'P', long, value_delimiter = ',', num_args = 1..=5, ignore_case = true)]
+        /// Platforms to build (default: all)
+        platforms: Option<Vec<Platform>>,
+
+        #[arg(short = 'p', long = "package")]
+        /// Package only the given workspace member (may be repeated)
+        packages: Vec<String>,
+
         #[arg(long)]
+        /// Package every crate of the workspace that depends on uniffi or the rusify macros
+        workspace: bool,
+
+        #[arg(short, long, overrides_with = "no_release")]
+        /// Build package optimized for release (default: debug)
+        release: bool,
+
+        #[arg(long, overrides_with = "release")]
+        /// Build in debug mode even if release is configured for the crate
+        no_release: bool,
+
+        #[arg(short = 'F', long, trailing_var_arg = true)]
+        features: Option<Vec<String>>,
+
+        #[arg(long, overrides_with = "no_all_features")]
+        all_features: bool,
+
+        #[arg(long, overrides_with = "all_features")]
+        /// Do not activate all features even if configured for the crate
+        no_all_features: bool,
+
+        #[arg(long, overrides_with = "default_features")]
         no_default_features: bool,
 
+        #[arg(long, overrides_with = "no_default_features")]
+        /// Activate the default features even if they are disabled for the crate
+        default_features: bool,
+
         #[arg(short, long, global = true)]
         /// Silence all output except errors and interactive prompts
         silent: bool,
@@ -109,10 +434,50 @@ enum Commands {
     },
 }
 
+/// Parses a `<platform>=<version>` pair
+fn parse_deployment_target(value: &str) -> std::result::Result<(ApplePlatform, String), String> {
+    let (platform, version) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected <platform>=<version>, got \"{value}\""))?;
+    let platform = <ApplePlatform as clap::ValueEnum>::from_str(platform, true)?;
+
+    Ok((platform, version.to_string()))
+}
+
+/// Turns a flag and its negation into an override of the project configuration
+fn flag(set: bool, unset: bool) -> Option<bool> {
+    match (set, unset) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+/// Returns the build mode passed on the command line, if any
+fn mode(release: bool, no_release: bool) -> Option<Mode> {
+    flag(release, no_release).map(|release| if release { Mode::Release } else { Mode::Debug })
+}
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
-    let result = match cli.command {
+    if let Err(e) = run(cli) {
+        eprintln!("\n");
+        eprintln!("Failed due to the following error: \n{}", e);
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    let out_dir = cli.out_dir;
+    let metadata_options = MetadataOptions {
+        manifest_path: cli.manifest_path,
+        offline: !cli.online,
+    };
+
+    match cli.command {
         Commands::Init { crate_name } => {
             rusify_cli::init::init_crate(crate_name);
             Ok(())
@@ -122,28 +487,52 @@ fn main() -> ExitCode {
             target,
             package_name,
             xcframework_name,
+            deployment_targets,
+            swift_tools_version,
+            distribution,
+            url_base,
+            apple_silicon_only,
+            no_apple_silicon_only,
+            packages,
+            workspace,
             release,
+            no_release,
             lib_type,
             suppress_warnings,
+            no_suppress_warnings,
             features,
             all_features,
+            no_all_features,
             no_default_features,
+            default_features,
             silent,
             accept_all,
         } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
             rusify_cli::apple::package::build_swift_package(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
                 platforms,
                 target.as_deref(),
                 package_name,
                 xcframework_name,
-                suppress_warnings,
-                Config { silent, accept_all },
-                if release { Mode::Release } else { Mode::Debug },
+                deployment_targets,
+                swift_tools_version,
+                distribution,
+                url_base,
+                flag(apple_silicon_only, no_apple_silicon_only),
+                flag(suppress_warnings, no_suppress_warnings),
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
                 lib_type,
-                FeatureOptions {
+                FeatureArgs {
                     features,
-                    all_features,
-                    no_default_features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
                 },
             )
         }
@@ -151,39 +540,192 @@ fn main() -> ExitCode {
             archs,
             target,
             api_level,
+            ndk_version,
             package_name,
             aar_name,
+            output,
+            maven_repo,
+            packages,
+            workspace,
             release,
+            no_release,
             lib_type,
             features,
             all_features,
+            no_all_features,
             no_default_features,
+            default_features,
             silent,
             accept_all,
         } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
             rusify_cli::android::package::build_android_package(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
                 archs,
                 target.as_deref(),
                 api_level,
+                ndk_version,
                 package_name,
                 aar_name,
-                Config { silent, accept_all },
-                if release { Mode::Release } else { Mode::Debug },
+                output,
+                maven_repo,
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
                 lib_type,
-                FeatureOptions {
+                FeatureArgs {
                     features,
-                    all_features,
-                    no_default_features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
+                },
+            )
+        }
+        Commands::BuildJvm {
+            targets,
+            package_name,
+            jar_name,
+            packages,
+            workspace,
+            release,
+            no_release,
+            features,
+            all_features,
+            no_all_features,
+            no_default_features,
+            default_features,
+            silent,
+            accept_all,
+        } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
+            rusify_cli::jvm::package::build_jvm_package(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
+                targets,
+                package_name,
+                jar_name,
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
+                FeatureArgs {
+                    features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
+                },
+            )
+        }
+        Commands::BuildOhos {
+            archs,
+            target,
+            package_name,
+            har_name,
+            packages,
+            workspace,
+            release,
+            no_release,
+            features,
+            all_features,
+            no_all_features,
+            no_default_features,
+            default_features,
+            silent,
+            accept_all,
+        } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
+            rusify_cli::ohos::package::build_ohos_package(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
+                archs,
+                target.as_deref(),
+                package_name,
+                har_name,
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
+                FeatureArgs {
+                    features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
+                },
+            )
+        }
+        Commands::BuildWasm {
+            flavor,
+            package_name,
+            pkg_dir,
+            packages,
+            workspace,
+            release,
+            no_release,
+            features,
+            all_features,
+            no_all_features,
+            no_default_features,
+            default_features,
+            silent,
+            accept_all,
+        } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
+            rusify_cli::wasm::package::build_wasm_package(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
+                flavor,
+                package_name,
+                pkg_dir,
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
+                FeatureArgs {
+                    features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
+                },
+            )
+        }
+        Commands::Doctor { platforms, strict } => rusify_cli::doctor::doctor(platforms, strict),
+        Commands::BuildAll {
+            platforms,
+            packages,
+            workspace,
+            release,
+            no_release,
+            features,
+            all_features,
+            no_all_features,
+            no_default_features,
+            default_features,
+            silent,
+            accept_all,
+        } => {
+            let cargo_workspace = Workspace::load(&metadata_options)?;
+            rusify_cli::build::build_all(
+                &cargo_workspace,
+                &CrateSelection { packages, workspace },
+                platforms,
+                Config {
+                    silent,
+                    accept_all,
+                    out_dir,
+                },
+                mode(release, no_release),
+                FeatureArgs {
+                    features,
+                    all_features: flag(all_features, no_all_features),
+                    no_default_features: flag(no_default_features, default_features),
                 },
             )
         }
-    };
-
-    if let Err(e) = result {
-        eprintln!("\n");
-        eprintln!("Failed due to the following error: \n{}", e);
-        ExitCode::FAILURE
-    } else {
-        ExitCode::SUCCESS
     }
 }
\ No newline at end of file
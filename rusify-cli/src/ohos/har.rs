@@ -0,0 +1,153 @@
+use anyhow::{Context, Result};
+use askama::Template;
+use camino::Utf8Path;
+use flate2::{write::GzEncoder, Compression};
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+use crate::common::models::{Config, Mode};
+use crate::common::path::recreate_dir;
+use crate::common::templating::{IndexEts, ModuleJson5, NativeOhPackageJson5, OhPackageJson5};
+use crate::console::{messages::info, step::run_step};
+use crate::ohos::ohos_arch::{library_file_name, OhosArch};
+
+/// Package information written to `oh-package.json5`
+pub(crate) struct HarPackageInfo<'a> {
+    pub(crate) package_name: &'a str,
+    pub(crate) version: &'a str,
+    pub(crate) description: &'a str,
+    pub(crate) license: &'a str,
+}
+
+pub(crate) fn create_har_with_output(
+    archs: &[OhosArch],
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_info: &HarPackageInfo,
+    har_name: &str,
+    mode: Mode,
+    config: &Config,
+) -> Result<()> {
+    run_step(config, "Creating HarmonyOS HAR package...", || {
+        let output_dir = config.output_path(har_name).into_std_path_buf();
+        recreate_dir(&output_dir)?;
+
+        create_har(
+            archs,
+            target_dir,
+            lib_name,
+            package_info,
+            config.generated_dir().as_std_path(),
+            &output_dir,
+            mode,
+        )
+    })
+    .map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to create HAR package due to the following error: \n {}",
+            e
+        )
+    })?;
+
+    info!(config, "Created HAR package at: {}.har", config.output_path(har_name));
+
+    Ok(())
+}
+
+fn create_har(
+    archs: &[OhosArch],
+    target_dir: &Utf8Path,
+    lib_name: &str,
+    package_info: &HarPackageInfo,
+    generated_dir: &Path,
+    output_dir: &Path,
+    mode: Mode,
+) -> Result<()> {
+    // Copy native libraries
+    let libs_dir = output_dir.join("libs");
+    for arch in archs {
+        let arch_dir = libs_dir.join(arch.to_arch());
+        create_dir_all(&arch_dir)?;
+
+        fs::copy(
+            arch.library_path(target_dir, lib_name, mode),
+            arch_dir.join(library_file_name(lib_name)),
+        )
+        .context(format!("Failed to copy library for {}", arch.display_name()))?;
+    }
+
+    // Module description
+    let main_dir = output_dir.join("src").join("main");
+    create_dir_all(&main_dir)?;
+    let module_json = ModuleJson5 {
+        module_name: lib_name,
+    };
+    fs::write(
+        main_dir.join("module.json5"),
+        module_json
+            .render()
+            .context("Failed to render module.json5 template")?,
+    )
+    .context("Failed to write module.json5")?;
+
+    // Type declarations of the native library
+    let types_dir = main_dir
+        .join("cpp")
+        .join("types")
+        .join(format!("lib{lib_name}"));
+    create_dir_all(&types_dir)?;
+    fs::copy(
+        generated_dir.join("arkts").join("index.d.ts"),
+        types_dir.join("index.d.ts"),
+    )
+    .context("Failed to copy ArkTS declarations")?;
+
+    let native_package = NativeOhPackageJson5 {
+        lib_name,
+        version: package_info.version,
+    };
+    fs::write(
+        types_dir.join("oh-package.json5"),
+        native_package
+            .render()
+            .context("Failed to render oh-package.json5 template")?,
+    )
+    .context("Failed to write oh-package.json5")?;
+
+    // Package manifest and entry point
+    let oh_package = OhPackageJson5 {
+        package_name: package_info.package_name,
+        version: package_info.version,
+        description: package_info.description,
+        license: package_info.license,
+        lib_name,
+    };
+    fs::write(
+        output_dir.join("oh-package.json5"),
+        oh_package
+            .render()
+            .context("Failed to render oh-package.json5 template")?,
+    )
+    .context("Failed to write oh-package.json5")?;
+
+    let index = IndexEts { lib_name };
+    fs::write(
+        output_dir.join("Index.ets"),
+        index.render().context("Failed to render Index.ets template")?,
+    )
+    .context("Failed to write Index.ets")?;
+
+    // A HAR is a gzipped tarball with all files below a `package/` directory
+    let har_path = PathBuf::from(format!("{}.har", output_dir.to_str().unwrap()));
+    let har_file = fs::File::create(&har_path).context("Failed to create HAR file")?;
+
+    let mut tar = tar::Builder::new(GzEncoder::new(har_file, Compression::default()));
+    tar.mode(tar::HeaderMode::Deterministic);
+    tar.append_dir_all("package", output_dir)
+        .context("Failed to add files to HAR archive")?;
+    tar.into_inner()
+        .and_then(|encoder| encoder.finish())
+        .context("Failed to finalize HAR archive")?;
+
+    Ok(())
+}
@@ -1,5 +1,11 @@
+use std::path::Path;
+use std::process::Command;
 use std::str::FromStr;
 
+use crate::common::models::{FeatureOptions, Mode};
+use camino::Utf8Path;
+use execute::command;
+
 #[derive(clap::ValueEnum, Clone, Copy, Debug)]
 pub enum OhosArch {
     ARM64,
@@ -12,6 +18,15 @@ impl OhosArch {
         [Self::ARM64, Self::ARM32, Self::X86_64]
     }
 
+    pub(crate) fn display_name(&self) -> String {
+        match self {
+            OhosArch::ARM64 => "ARM64-v8a",
+            OhosArch::ARM32 => "ARMv7-a",
+            OhosArch::X86_64 => "x86_64",
+        }
+        .to_string()
+    }
+
     pub fn to_arch(self) -> &'static str {
         match self {
             OhosArch::ARM64 => "arm64-v8a",
@@ -41,6 +56,93 @@ impl OhosArch {
             OhosArch::X86_64 => "x86_64-unknown-linux-ohos",
         }
     }
+
+    /// Generates the cargo command building this architecture with the OpenHarmony NDK toolchain
+    ///
+    /// The compiler, archiver and linker are passed to the spawned command via environment variables,
+    /// so neither the process environment nor any cargo configuration file is modified.
+    pub(crate) fn cargo_build_command(
+        &self,
+        manifest_path: &Utf8Path,
+        ndk_home: &Path,
+        mode: Mode,
+        features: &FeatureOptions,
+    ) -> Command {
+        let llvm_bin = ndk_home.join("native").join("llvm").join("bin");
+        let sysroot = ndk_home.join("native").join("sysroot");
+        let exe_ext = if cfg!(target_os = "windows") { ".exe" } else { "" };
+
+        let clang = llvm_bin.join(format!("clang{exe_ext}"));
+        let clang_cpp = llvm_bin.join(format!("clang++{exe_ext}"));
+        let ar = llvm_bin.join(format!("llvm-ar{exe_ext}"));
+        let cflags = format!(
+            "--target={} --sysroot={} -D__MUSL__",
+            self.c_target(),
+            sysroot.display()
+        );
+        let rustflags = format!(
+            "-Clink-arg=--target={} -Clink-arg=--sysroot={}",
+            self.c_target(),
+            sysroot.display()
+        );
+
+        let mut cmd = command("cargo build");
+        cmd.arg("--manifest-path").arg(manifest_path);
+        cmd.arg("--target").arg(self.rust_target());
+
+        match mode {
+            Mode::Debug => {}
+            Mode::Release => {
+                cmd.arg("--release");
+            }
+        }
+
+        if let Some(features) = &features.features {
+            cmd.arg("--features").arg(features.join(","));
+        }
+        if features.all_features {
+            cmd.arg("--all-features");
+        }
+        if features.no_default_features {
+            cmd.arg("--no-default-features");
+        }
+
+        let target_var = self.rust_target().replace('-', "_");
+        cmd.env(format!("CC_{target_var}"), &clang);
+        cmd.env(format!("CXX_{target_var}"), &clang_cpp);
+        cmd.env(format!("AR_{target_var}"), &ar);
+        cmd.env(format!("CFLAGS_{target_var}"), &cflags);
+        cmd.env(format!("CXXFLAGS_{target_var}"), &cflags);
+        cmd.env(format!("CARGO_TARGET_{}_LINKER", self.rust_link_target()), &clang);
+        cmd.env(format!("CARGO_TARGET_{}_AR", self.rust_link_target()), &ar);
+        cmd.env(format!("CARGO_TARGET_{}_RUSTFLAGS", self.rust_link_target()), rustflags);
+
+        cmd
+    }
+
+    pub fn library_directory(&self, target_dir: &Utf8Path, mode: Mode) -> String {
+        format!("{target_dir}/{}/{mode}", self.rust_target())
+    }
+
+    pub fn library_path(&self, target_dir: &Utf8Path, lib_name: &str, mode: Mode) -> String {
+        format!(
+            "{}/{}",
+            self.library_directory(target_dir, mode),
+            library_file_name(lib_name)
+        )
+    }
+}
+
+pub fn library_file_name(lib_name: &str) -> String {
+    format!("lib{lib_name}.so")
+}
+
+/// Reads the version of the native toolchain from `native/oh-uni-package.json` in the SDK
+pub(crate) fn sdk_version(ndk_home: &Path) -> Option<String> {
+    let package = std::fs::read_to_string(ndk_home.join("native").join("oh-uni-package.json")).ok()?;
+    let package: serde_json::Value = serde_json::from_str(&package).ok()?;
+
+    package["version"].as_str().map(str::to_string)
 }
 
 impl FromStr for OhosArch {
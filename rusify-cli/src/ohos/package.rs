@@ -0,0 +1,240 @@
+use anyhow::{anyhow, Context, Result};
+use camino::{Utf8Path, Utf8PathBuf};
+use cargo_metadata::Package;
+use dialoguer::{Input, MultiSelect};
+use std::path::{Path, PathBuf};
+
+use crate::common::{
+    metadata::{library_target, Workspace},
+    models::{default_output_name, Config, CrateSelection, FeatureArgs, FeatureOptions, Mode},
+    path::recreate_dir,
+    project::ProjectConfig,
+    toolchain::ensure_targets_installed,
+};
+use crate::console::{messages::*, step::run_step_with_commands, theme::prompt_theme};
+use crate::ffi::arkts::generate_arkts_declarations_with_output;
+use crate::ohos::har::{create_har_with_output, HarPackageInfo};
+use crate::ohos::ohos_arch::OhosArch;
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_ohos_package(
+    workspace: &Workspace,
+    selection: &CrateSelection,
+    architectures: Option<Vec<OhosArch>>,
+    build_target: Option<&str>,
+    package_name: Option<String>,
+    har_name: Option<String>,
+    config: Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let crates = workspace.selected_crates(selection)?;
+
+    if crates.len() == 1 {
+        return build_ohos_package_for_crate(
+            workspace,
+            crates[0],
+            architectures,
+            build_target,
+            package_name,
+            har_name,
+            false,
+            &config,
+            mode,
+            features,
+        );
+    } else if package_name.is_some() {
+        return Err(anyhow!(
+            "Package name can only be specified when building a single crate!"
+        ));
+    } else if har_name.is_some() {
+        return Err(anyhow!(
+            "HAR name can only be specified when building a single crate!"
+        ));
+    }
+
+    crates
+        .iter()
+        .map(|current_crate| {
+            info!(&config, "Packaging crate {}", current_crate.name);
+            build_ohos_package_for_crate(
+                workspace,
+                current_crate,
+                architectures.clone(),
+                build_target,
+                None,
+                None,
+                true,
+                &config,
+                mode,
+                features.clone(),
+            )
+        })
+        .filter_map(|result| result.err())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .reduce(|acc, err| acc.context(err))
+        .map_or(Ok(()), Err)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_ohos_package_for_crate(
+    workspace: &Workspace,
+    current_crate: &Package,
+    architectures: Option<Vec<OhosArch>>,
+    build_target: Option<&str>,
+    package_name: Option<String>,
+    har_name: Option<String>,
+    multiple_crates: bool,
+    config: &Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let ndk_home = std::env::var("OHOS_NDK_HOME").map_err(|e| {
+        anyhow!(
+            "OHOS_NDK_HOME environment variable is not set. Please install the OpenHarmony SDK and point this variable to its native toolchain: {}",
+            e
+        )
+    })?;
+    let ndk_home = PathBuf::from(ndk_home);
+    if !ndk_home.join("native").join("llvm").exists() {
+        return Err(anyhow!(
+            "OpenHarmony toolchain not found in {}. Please check your OHOS_NDK_HOME setting.",
+            ndk_home.display()
+        ));
+    }
+
+    let lib = library_target(current_crate)?;
+
+    let project = ProjectConfig::load(current_crate, config)?;
+    let mode = project.mode(mode);
+    let mut features = project.features(features);
+    let har_name = har_name
+        .or(project.ohos.har_name)
+        .unwrap_or_else(|| default_output_name("RustHar", current_crate, multiple_crates));
+
+    let crate_name = current_crate.name.to_lowercase();
+    let package_name = package_name
+        .or(project.ohos.package_name)
+        .unwrap_or_else(|| prompt_package_name(&crate_name, config.accept_all));
+
+    let mut architectures = architectures
+        .or(project.ohos.archs)
+        .unwrap_or_else(|| prompt_architectures(config.accept_all));
+
+    if let Some(build_target) = build_target {
+        architectures.retain(|arch| arch.rust_target() == build_target);
+        if architectures.is_empty() {
+            return Err(anyhow!("No matching build target for {}", build_target));
+        }
+    }
+
+    if architectures.is_empty() {
+        return Err(anyhow!("At least 1 architecture needs to be selected!"));
+    }
+
+    let installable: Vec<_> = architectures.iter().map(|arch| arch.rust_target()).collect();
+    ensure_targets_installed(&installable, config)?;
+
+    // The rusify macros only emit napi attributes when the `ohos` feature is enabled
+    if current_crate.features.contains_key("ohos") && !features.all_features {
+        let enabled = features.features.get_or_insert_with(Vec::new);
+        if !enabled.iter().any(|f| f == "ohos") {
+            enabled.push("ohos".to_string());
+        }
+    }
+
+    let generated_dir = config.generated_dir();
+    recreate_dir(&generated_dir).context("Could not create generated directory!")?;
+    // napi writes the type definitions from within the build, so the path has to be absolute
+    let type_def_dir: Utf8PathBuf = std::path::absolute(generated_dir.join("type_defs"))?
+        .try_into()?;
+    std::fs::create_dir(&type_def_dir)?;
+
+    let crate_name = lib.name.replace('-', "_");
+    for arch in &architectures {
+        build_with_output(
+            arch,
+            &current_crate.manifest_path,
+            &ndk_home,
+            &type_def_dir,
+            mode,
+            config,
+            &features,
+        )?;
+    }
+
+    generate_arkts_declarations_with_output(&type_def_dir, &crate_name, config)?;
+
+    let version = current_crate.version.to_string();
+    create_har_with_output(
+        &architectures,
+        &workspace.target_dir(),
+        &crate_name,
+        &HarPackageInfo {
+            package_name: &package_name,
+            version: &version,
+            description: current_crate.description.as_deref().unwrap_or_default(),
+            license: current_crate.license.as_deref().unwrap_or_default(),
+        },
+        &har_name,
+        mode,
+        config,
+    )?;
+
+    Ok(())
+}
+
+fn prompt_architectures(accept_all: bool) -> Vec<OhosArch> {
+    let architectures = OhosArch::all();
+    let items = architectures.map(|a| a.display_name());
+
+    if accept_all {
+        return architectures.to_vec();
+    }
+
+    let theme = prompt_theme();
+    let selector = MultiSelect::with_theme(&theme)
+        .items(&items)
+        .with_prompt("Select Target Architectures")
+        .defaults(&[true, false, true]); // Default to ARM64 and the x86_64 emulator
+
+    let chosen: Vec<usize> = selector.interact().unwrap();
+
+    chosen.into_iter().map(|i| architectures[i]).collect()
+}
+
+fn prompt_package_name(default: &str, accept_all: bool) -> String {
+    if accept_all {
+        return default.to_string();
+    }
+
+    let theme = prompt_theme();
+    Input::with_theme(&theme)
+        .with_prompt("HAR Package Name")
+        .default(default.to_string())
+        .interact_text()
+        .unwrap()
+}
+
+fn build_with_output(
+    arch: &OhosArch,
+    manifest_path: &Utf8Path,
+    ndk_home: &Path,
+    type_def_dir: &Utf8Path,
+    mode: Mode,
+    config: &Config,
+    features: &FeatureOptions,
+) -> Result<()> {
+    let mut command = arch.cargo_build_command(manifest_path, ndk_home, mode, features);
+    command.env("CARGO_TERM_COLOR", "always");
+    command.env("TYPE_DEF_TMP_PATH", type_def_dir);
+
+    run_step_with_commands(
+        config,
+        format!("Building target {}", arch.display_name()),
+        &mut [command],
+    )?;
+
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use askama::Template;
+use std::fs;
+use std::ops::Not;
+use std::path::Path;
+
+use crate::common::models::Config;
+use crate::common::templating::PackageJson;
+use crate::console::step::run_step;
+use crate::console::{MainSpinner, Ticking};
+use crate::wasm::wasm_target::WasmFlavor;
+
+/// Package information written to `package.json`
+pub(crate) struct NpmPackageInfo<'a> {
+    pub(crate) package_name: &'a str,
+    pub(crate) version: &'a str,
+    pub(crate) description: &'a str,
+    pub(crate) license: &'a str,
+}
+
+pub(crate) fn create_npm_package_with_output(
+    package_info: &NpmPackageInfo,
+    lib_name: &str,
+    flavor: WasmFlavor,
+    output_dir: &Path,
+    config: &Config,
+) -> Result<()> {
+    run_step(
+        config,
+        format!("Creating npm package '{}'...", package_info.package_name),
+        || create_npm_package(package_info, lib_name, flavor, output_dir),
+    )?;
+
+    let spinner = config.silent.not().then(|| {
+        MainSpinner::with_message(format!(
+            "Successfully created npm package in '{}/'!",
+            output_dir.display()
+        ))
+    });
+    spinner.finish();
+
+    Ok(())
+}
+
+/// Writes the `package.json` for the bindings generated into `output_dir`
+///
+/// **Note**: This method assumes that wasm-bindgen already emitted its output into `output_dir`
+pub(crate) fn create_npm_package(
+    package_info: &NpmPackageInfo,
+    lib_name: &str,
+    flavor: WasmFlavor,
+    output_dir: &Path,
+) -> Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(output_dir).context("Could not read generated bindings")? {
+        let file_name = entry?.file_name().to_string_lossy().to_string();
+        if file_name != "package.json" {
+            files.push(file_name);
+        }
+    }
+    files.sort();
+
+    let package_json = PackageJson {
+        package_name: package_info.package_name,
+        version: package_info.version,
+        description: package_info.description,
+        license: package_info.license,
+        lib_name,
+        is_es_module: flavor.is_es_module(),
+        files: &files,
+    };
+
+    fs::write(
+        output_dir.join("package.json"),
+        package_json
+            .render()
+            .context("Failed to render package.json template")?,
+    )
+    .context("Could not write package.json")?;
+
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use anyhow::{anyhow, Context, Result};
+use cargo_metadata::Package;
+use convert_case::{Case, Casing};
+use dialoguer::{Input, Select};
+use std::path::Path;
+
+use crate::common::{
+    metadata::{library_target, Workspace},
+    models::{default_output_name, Config, CrateSelection, FeatureArgs, Mode},
+    path::recreate_dir,
+    project::ProjectConfig,
+    toolchain::ensure_targets_installed,
+};
+use crate::console::{messages::*, step::run_step_with_commands, theme::prompt_theme};
+use crate::ffi::typescript::generate_typescript_bindings_with_output;
+use crate::wasm::npm::{create_npm_package_with_output, NpmPackageInfo};
+use crate::wasm::wasm_target::{cargo_build_command, library_path, WasmFlavor, WASM_TARGET};
+
+#[allow(clippy::too_many_arguments)]
+pub fn build_wasm_package(
+    workspace: &Workspace,
+    selection: &CrateSelection,
+    flavor: Option<WasmFlavor>,
+    package_name: Option<String>,
+    pkg_dir: Option<String>,
+    config: Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let crates = workspace.selected_crates(selection)?;
+
+    if crates.len() == 1 {
+        return build_wasm_package_for_crate(
+            workspace,
+            crates[0],
+            flavor,
+            package_name,
+            pkg_dir,
+            false,
+            &config,
+            mode,
+            features,
+        );
+    } else if package_name.is_some() {
+        return Err(anyhow!(
+            "Package name can only be specified when building a single crate!"
+        ));
+    } else if pkg_dir.is_some() {
+        return Err(anyhow!(
+            "Package directory can only be specified when building a single crate!"
+        ));
+    }
+
+    crates
+        .iter()
+        .map(|current_crate| {
+            info!(&config, "Packaging crate {}", current_crate.name);
+            build_wasm_package_for_crate(
+                workspace,
+                current_crate,
+                flavor,
+                None,
+                None,
+                true,
+                &config,
+                mode,
+                features.clone(),
+            )
+        })
+        .filter_map(|result| result.err())
+        .collect::<Vec<_>>()
+        .into_iter()
+        .reduce(|acc, err| acc.context(err))
+        .map_or(Ok(()), Err)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn build_wasm_package_for_crate(
+    workspace: &Workspace,
+    current_crate: &Package,
+    flavor: Option<WasmFlavor>,
+    package_name: Option<String>,
+    pkg_dir: Option<String>,
+    multiple_crates: bool,
+    config: &Config,
+    mode: Option<Mode>,
+    features: FeatureArgs,
+) -> Result<()> {
+    let lib = library_target(current_crate)?;
+
+    let project = ProjectConfig::load(current_crate, config)?;
+    let mode = project.mode(mode);
+    let mut features = project.features(features);
+    let pkg_dir = pkg_dir
+        .or(project.wasm.pkg_dir)
+        .unwrap_or_else(|| default_output_name("pkg", current_crate, multiple_crates));
+
+    let package_name = package_name
+        .or(project.wasm.package_name)
+        .unwrap_or_else(|| prompt_package_name(&current_crate.name, config.accept_all));
+    let flavor = flavor
+        .or(project.wasm.flavor)
+        .unwrap_or_else(|| prompt_flavor(config.accept_all));
+
+    // The rusify macros only emit wasm-bindgen attributes when the `wasm` feature is enabled
+    if current_crate.features.contains_key("wasm") && !features.all_features {
+        let enabled = features.features.get_or_insert_with(Vec::new);
+        if !enabled.iter().any(|f| f == "wasm") {
+            enabled.push("wasm".to_string());
+        }
+    }
+
+    ensure_targets_installed(&[WASM_TARGET], config)?;
+
+    let mut command = cargo_build_command(&current_crate.manifest_path, mode, &features);
+    command.env("CARGO_TERM_COLOR", "always");
+    run_step_with_commands(
+        config,
+        format!("Building target {}", WASM_TARGET),
+        &mut [command],
+    )?;
+
+    let crate_name = lib.name.replace('-', "_");
+    let wasm_path = library_path(&workspace.target_dir(), &crate_name, mode);
+    if !Path::new(&wasm_path).exists() {
+        return Err(anyhow!(
+            "Library file does not exist: {}. Make sure the crate-type in Cargo.toml includes \"cdylib\".",
+            wasm_path
+        ));
+    }
+
+    let output_dir = config.output_path(&pkg_dir).into_std_path_buf();
+    recreate_dir(&output_dir).context("Could not create package output directory!")?;
+
+    generate_typescript_bindings_with_output(&wasm_path, &crate_name, flavor, &output_dir, config)?;
+
+    let version = current_crate.version.to_string();
+    create_npm_package_with_output(
+        &NpmPackageInfo {
+            package_name: &package_name,
+            version: &version,
+            description: current_crate.description.as_deref().unwrap_or_default(),
+            license: current_crate.license.as_deref().unwrap_or_default(),
+        },
+        &crate_name,
+        flavor,
+        &output_dir,
+        config,
+    )?;
+
+    Ok(())
+}
+
+fn prompt_flavor(accept_all: bool) -> WasmFlavor {
+    let flavors = WasmFlavor::all();
+    let items = flavors.map(|f| f.display_name());
+
+    if accept_all {
+        return WasmFlavor::Bundler;
+    }
+
+    let theme = prompt_theme();
+    let chosen = Select::with_theme(&theme)
+        .items(&items)
+        .with_prompt("Select JavaScript Output Flavor")
+        .default(1)
+        .interact()
+        .unwrap();
+
+    flavors[chosen]
+}
+
+fn prompt_package_name(crate_name: &str, accept_all: bool) -> String {
+    let default = crate_name.to_case(Case::Kebab);
+
+    if accept_all {
+        return default;
+    }
+
+    let theme = prompt_theme();
+    Input::with_theme(&theme)
+        .with_prompt("npm Package Name")
+        .default(default)
+        .interact_text()
+        .unwrap()
+}
@@ -0,0 +1,71 @@
+use std::process::Command;
+
+use crate::common::models::{FeatureOptions, Mode};
+use camino::Utf8Path;
+use execute::command;
+
+pub const WASM_TARGET: &str = "wasm32-unknown-unknown";
+
+/// The JavaScript module flavor emitted by wasm-bindgen
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WasmFlavor {
+    /// ES module that fetches and instantiates the .wasm file itself (browsers without bundler)
+    Web,
+    /// ES module importing the .wasm file, to be consumed by bundlers like webpack or vite
+    Bundler,
+    /// CommonJS module loading the .wasm file synchronously from disk
+    NodeJs,
+}
+
+impl WasmFlavor {
+    pub(crate) fn display_name(&self) -> String {
+        match self {
+            WasmFlavor::Web => "Web",
+            WasmFlavor::Bundler => "Bundler",
+            WasmFlavor::NodeJs => "Node.js",
+        }
+        .to_string()
+    }
+
+    pub(crate) fn all() -> [Self; 3] {
+        [Self::Web, Self::Bundler, Self::NodeJs]
+    }
+
+    /// Whether the generated JavaScript is an ES module
+    pub fn is_es_module(&self) -> bool {
+        !matches!(self, WasmFlavor::NodeJs)
+    }
+}
+
+pub(crate) fn cargo_build_command(
+    manifest_path: &Utf8Path,
+    mode: Mode,
+    features: &FeatureOptions,
+) -> Command {
+    let mut cmd = command("cargo build");
+    cmd.arg("--manifest-path").arg(manifest_path);
+    cmd.arg("--target").arg(WASM_TARGET);
+
+    match mode {
+        Mode::Debug => {}
+        Mode::Release => {
+            cmd.arg("--release");
+        }
+    }
+
+    if let Some(features) = &features.features {
+        cmd.arg("--features").arg(features.join(","));
+    }
+    if features.all_features {
+        cmd.arg("--all-features");
+    }
+    if features.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+
+    cmd
+}
+
+pub fn library_path(target_dir: &Utf8Path, lib_name: &str, mode: Mode) -> String {
+    format!("{target_dir}/{WASM_TARGET}/{mode}/{lib_name}.wasm")
+}